@@ -0,0 +1,228 @@
+use crate::{
+    error::{Error, Result},
+    library::path_str,
+    load_study_book,
+    study_book::schedule::now,
+};
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+// Backups kept for each book, the oldest ones are removed first.
+pub const MAX_BACKUPS: usize = 10;
+
+// A book saved many times in a row (e.g. after every review) is backed up once.
+const BACKUP_INTERVAL: u64 = 60 * 60;
+
+const BACKUPS_DIR: &str = ".backups";
+
+// A snapshot of a book taken before it was overwritten.
+#[derive(Debug, PartialEq)]
+pub struct Backup {
+    pub path: String,
+    // Unix timestamp in seconds
+    pub timestamp: u64,
+}
+
+// .backups/<book name>/ next to the book.
+fn backups_dir(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let name = path.file_stem().unwrap_or_default();
+
+    path.with_file_name(BACKUPS_DIR).join(name)
+}
+
+// Write to a temporary file first, then move it over `path`,
+//  so the file is never left half written.
+pub fn write_atomic(path: &str, contents: &str) -> Result<()> {
+    if let Some(dir) = Path::new(path)
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+    {
+        fs::create_dir_all(dir).map_err(|err| Error::io(path, err))?;
+    }
+
+    let tmp = format!("{}.tmp", path);
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    };
+    write().map_err(|err| Error::io(&tmp, err))?;
+
+    fs::rename(&tmp, path).map_err(|err| Error::io(path, err))
+}
+
+// The backups of the book at `path`, the newest first.
+pub fn list(path: &str) -> Result<Vec<Backup>> {
+    let dir = backups_dir(path);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(Error::io(&path_str(&dir), err)),
+    };
+
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| {
+            let timestamp = p.file_stem()?.to_str()?.parse().ok()?;
+            Some(Backup {
+                path: path_str(&p),
+                timestamp,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.timestamp));
+
+    Ok(backups)
+}
+
+// Copy the book at `path` to its backups, unless a recent backup exists and `force` is false.
+// The backups beyond MAX_BACKUPS are removed.
+pub fn back_up(path: &str, timestamp: u64, force: bool) -> Result<Option<Backup>> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+
+    let backups = list(path)?;
+    let recent = backups
+        .first()
+        .is_some_and(|b| timestamp < b.timestamp + BACKUP_INTERVAL);
+    if recent && !force {
+        return Ok(None);
+    }
+
+    // Never overwrite a backup taken in the same second
+    let timestamp = backups
+        .first()
+        .map_or(timestamp, |b| timestamp.max(b.timestamp + 1));
+
+    let dir = backups_dir(path);
+    fs::create_dir_all(&dir).map_err(|err| Error::io(&path_str(&dir), err))?;
+    let backup = Backup {
+        path: path_str(&dir.join(format!("{}.json", timestamp))),
+        timestamp,
+    };
+    fs::copy(path, &backup.path).map_err(|err| Error::io(&backup.path, err))?;
+
+    for old in backups.iter().skip(MAX_BACKUPS - 1) {
+        fs::remove_file(&old.path).map_err(|err| Error::io(&old.path, err))?;
+    }
+
+    Ok(Some(backup))
+}
+
+// Roll the book at `path` back to `backup`, keeping the current book as a backup.
+pub fn restore(path: &str, backup: &Backup) -> Result<()> {
+    // Only restore a valid book
+    load_study_book(&backup.path)?.ok_or_else(|| Error::NoBook(backup.path.to_owned()))?;
+    let contents = fs::read_to_string(&backup.path).map_err(|err| Error::io(&backup.path, err))?;
+
+    back_up(path, now(), true)?;
+    write_atomic(path, &contents)
+}
+
+// Move the backups along with a renamed book.
+pub fn rename(path: &str, new_path: &str) -> Result<()> {
+    let (from, to) = (backups_dir(path), backups_dir(new_path));
+    if !from.is_dir() {
+        return Ok(());
+    }
+
+    fs::rename(&from, &to).map_err(|err| Error::io(&path_str(&from), err))
+}
+
+// Drop the backups of a deleted book, so a new book of the same name doesn't restore them.
+pub fn remove(path: &str) -> Result<()> {
+    let dir = backups_dir(path);
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    fs::remove_dir_all(&dir).map_err(|err| Error::io(&path_str(&dir), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::study_book::StudyBook;
+
+    const DIR: &str = ".test/backup";
+
+    #[test]
+    fn can_back_up_and_restore() {
+        let _ = fs::remove_dir_all(DIR);
+        let path = format!("{}/book.json", DIR);
+
+        let first = StudyBook::from_article("<<工場・こうじょう>>です。").unwrap();
+        write_atomic(&path, &first.to_json().unwrap()).unwrap();
+        assert!(!Path::new(&format!("{}.tmp", path)).exists());
+
+        // Nothing to back up yet, then once per interval unless forced
+        assert_eq!(
+            back_up(&format!("{}/none.json", DIR), 100, false).unwrap(),
+            None
+        );
+        assert!(back_up(&path, 100, false).unwrap().is_some());
+        assert_eq!(back_up(&path, 200, false).unwrap(), None);
+        assert!(back_up(&path, 300, true).unwrap().is_some());
+        assert_eq!(back_up(&path, 300, true).unwrap().unwrap().timestamp, 301);
+
+        let second = StudyBook::from_article("<<停止・ていし>>します。").unwrap();
+        write_atomic(&path, &second.to_json().unwrap()).unwrap();
+
+        let backups = list(&path).unwrap();
+        assert_eq!(backups.len(), 3);
+        assert_eq!(backups[0].timestamp, 301);
+
+        restore(&path, &backups[0]).unwrap();
+        assert_eq!(
+            load_study_book(&path).unwrap().unwrap().to_json().unwrap(),
+            first.to_json().unwrap()
+        );
+        // The overwritten book is a backup too
+        assert_eq!(list(&path).unwrap().len(), 4);
+
+        fs::remove_dir_all(DIR).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_newest_backups() {
+        let dir = format!("{}_rotation", DIR);
+        let _ = fs::remove_dir_all(&dir);
+        let path = format!("{}/book.json", dir);
+        write_atomic(&path, "{}").unwrap();
+
+        for i in 0..(MAX_BACKUPS as u64 + 3) {
+            back_up(&path, i * BACKUP_INTERVAL, false).unwrap();
+        }
+
+        let backups = list(&path).unwrap();
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert_eq!(
+            backups[0].timestamp,
+            (MAX_BACKUPS as u64 + 2) * BACKUP_INTERVAL
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lists_no_backups_only_when_there_are_none() {
+        let dir = format!("{}_list", DIR);
+        let _ = fs::remove_dir_all(&dir);
+        let path = format!("{}/book.json", dir);
+        assert!(list(&path).unwrap().is_empty());
+
+        // The backups can't be read
+        fs::create_dir_all(format!("{}/{}", dir, BACKUPS_DIR)).unwrap();
+        fs::write(backups_dir(&path), "").unwrap();
+        assert!(matches!(list(&path), Err(Error::Io { .. })));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
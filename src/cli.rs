@@ -0,0 +1,602 @@
+use std::{fs, path::Path};
+
+use clap::Subcommand;
+use console::style;
+use learn_jp::{
+    backup,
+    error::{Error, Result},
+    history::{self, stats},
+    input::Source,
+    language::Lang,
+    library::Library,
+    load_study_book, open_study_book,
+    parser::config::ParserConfig,
+    profile::{Profile, Users, DEFAULT_USER},
+    session::{self, QuizMode},
+    study_book::{
+        merge::MergePolicy,
+        schedule::{self, DAY},
+        table::{Column, ColumnMapping, TableFormat},
+        word::WordEntry,
+        StudyBook,
+    },
+    ui, update_wordbook,
+};
+
+#[derive(clap::Parser)]
+#[command(
+    name = "clibook",
+    about = "A CLI wordbook app for learning language in the terminal."
+)]
+pub struct Cli {
+    /// Name of the book of the library to use, or the path of a book file
+    #[arg(long, global = true)]
+    pub book: Option<String>,
+
+    /// The user whose books and settings to use
+    #[arg(long, global = true)]
+    pub user: Option<String>,
+
+    /// Directory of the users and their books, instead of $XDG_DATA_HOME/clibook
+    #[arg(long, global = true)]
+    pub library: Option<String>,
+
+    /// Without a command, start the interactive flow
+    #[command(subcommand)]
+    pub command: Option<TopCommand>,
+}
+
+// User commands run before a user is picked, the other ones on a book of the user.
+#[derive(Subcommand)]
+pub enum TopCommand {
+    #[command(flatten)]
+    Book(Command),
+    /// Manage the users and their settings
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Add marked-up content to the book, from a file, stdin or your editor
+    Add {
+        /// The file to read, "-" for stdin. Without it, read the piped stdin or open the editor
+        file: Option<String>,
+        /// Write the content in $VISUAL or $EDITOR
+        #[arg(short, long)]
+        editor: bool,
+    },
+    /// Review the words due today, days starting at midnight UTC
+    Study,
+    /// Show the numbers of the book and of its reviews
+    Stats {
+        /// Days of reviews to look back on, and of due words to look ahead to, in UTC
+        #[arg(long, default_value_t = 7)]
+        days: usize,
+    },
+    /// List the words of the book
+    List {
+        /// Only the mastered words
+        #[arg(long, conflicts_with = "backlog")]
+        archived: bool,
+        /// Only the words still to learn
+        #[arg(long)]
+        backlog: bool,
+    },
+    /// Export the book as json, or as notes to import in Anki
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+    },
+    /// Merge a book exported as json, or a vocabulary list, into the book
+    Import {
+        file: String,
+        /// Guessed from the extension of the file by default
+        #[arg(short, long, value_enum)]
+        format: Option<ImportFormat>,
+        /// The column of the words in the list, by number from 1 or by header
+        #[arg(long, default_value = "1")]
+        word: Column,
+        /// The column of the readings
+        #[arg(long, default_value = "2")]
+        reading: Column,
+        /// The column of the meanings
+        #[arg(long, default_value = "3")]
+        meaning: Column,
+        /// The column of the example sentences
+        #[arg(long, default_value = "4")]
+        sentence: Column,
+        /// The first row of the list is a word rather than headers
+        #[arg(long)]
+        no_headers: bool,
+    },
+    /// Search words by word, reading or annotation
+    Search { query: String },
+    /// List the backups of the book, or roll it back to one of them
+    Restore {
+        /// The number of the backup in the list, 1 being the newest
+        backup: Option<usize>,
+    },
+    /// Manage the books of the library
+    Book {
+        #[command(subcommand)]
+        command: BookCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// Create a user and switch to it
+    Create { name: String },
+    /// List the users, the current one is marked with a *
+    List,
+    /// Use another user from now on
+    Switch { name: String },
+    /// Delete a user with all their books
+    Delete {
+        name: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Show the settings of the user, or change them
+    Settings {
+        /// Words to review a day, counted from midnight UTC, 0 for all the due words
+        #[arg(long)]
+        daily_goal: Option<usize>,
+        /// The quiz modes taking turns in the sessions, e.g. reading,cloze,meaning,choice
+        #[arg(long, value_delimiter = ',')]
+        quiz_modes: Option<Vec<QuizMode>>,
+        /// Show the other target words of the sentence in a cloze, or hide them too
+        #[arg(long)]
+        cloze_show_others: Option<bool>,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The book itself, to import back later
+    Json,
+    /// A TSV of cloze notes for Anki, one per word
+    Anki,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ImportFormat {
+    /// A book exported as json
+    Json,
+    /// A vocabulary list with comma separated columns
+    Csv,
+    /// A vocabulary list with tab separated columns, e.g. exported from Anki
+    Tsv,
+}
+
+impl ImportFormat {
+    fn guess(file: &str) -> ImportFormat {
+        match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => ImportFormat::Csv,
+            Some("tsv" | "txt") => ImportFormat::Tsv,
+            _ => ImportFormat::Json,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum BookCommand {
+    /// Create an empty book and switch to it
+    Create {
+        name: String,
+        /// The language studied in the book
+        #[arg(short, long, default_value = "japanese")]
+        language: Lang,
+    },
+    /// List the books, the current one is marked with a *
+    List,
+    /// Use another book from now on
+    Switch { name: String },
+    /// Rename a book
+    Rename { name: String, new_name: String },
+    /// Delete a book and its progress
+    Delete {
+        name: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+// The user, and their library and book a command works on.
+pub struct Context {
+    pub profile: Profile,
+    pub library: Library,
+    pub book: String,
+}
+
+// A --book with the json extension is a file, otherwise a book name.
+pub fn is_book_file(book: &str) -> bool {
+    book.ends_with(".json")
+}
+
+// `user` is the --user flag, used by the settings.
+pub fn run_user_command(command: UserCommand, users: &Users, user: Option<&str>) -> Result<()> {
+    match command {
+        UserCommand::Create { name } => {
+            users.create(&name)?;
+            println!("Welcome, {}! You are now the current user.", name);
+        }
+        UserCommand::List => {
+            let current = users.current()?;
+
+            for name in users.list()? {
+                match Some(&name) == current.as_ref() {
+                    true => println!("* {}", style(name).bold()),
+                    false => println!("  {}", name),
+                }
+            }
+        }
+        UserCommand::Switch { name } => {
+            users.switch(&name)?;
+            println!("Hi {}, your books are ready.", name);
+        }
+        UserCommand::Delete { name, yes } => {
+            let prompt = format!("Delete the user {} with all their books?", name);
+            if yes || ui::confirm(&prompt)? {
+                users.delete(&name)?;
+                println!("Deleted the user {}.", name);
+            }
+        }
+        UserCommand::Settings {
+            daily_goal,
+            quiz_modes,
+            cloze_show_others,
+        } => {
+            let name = match user {
+                Some(user) => user.to_owned(),
+                None => users.current()?.unwrap_or(DEFAULT_USER.to_owned()),
+            };
+            let mut profile = users.load(&name)?;
+
+            let changed =
+                daily_goal.is_some() || quiz_modes.is_some() || cloze_show_others.is_some();
+            let settings = &mut profile.settings;
+            if let Some(goal) = daily_goal {
+                settings.daily_goal = goal;
+            }
+            if let Some(modes) = quiz_modes {
+                settings.quiz_modes = modes;
+            }
+            if let Some(show) = cloze_show_others {
+                settings.cloze_show_others = show;
+            }
+            if changed {
+                profile.save()?;
+            }
+
+            let modes: Vec<&str> = profile
+                .settings
+                .quiz_modes
+                .iter()
+                .map(|m| m.name())
+                .collect();
+            println!("User:       {}", profile.name);
+            println!("Daily goal: {} words", profile.settings.daily_goal);
+            println!("Quiz modes: {}", modes.join(", "));
+            println!(
+                "Cloze:      {} the other words",
+                match profile.settings.cloze_show_others {
+                    true => "show",
+                    false => "hide",
+                }
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn run_book_command(command: BookCommand, library: &Library) -> Result<()> {
+    match command {
+        BookCommand::Create { name, language } => {
+            library.create(&name, language)?;
+            println!(
+                "Created the book {} to learn {}, it's now in use.",
+                name,
+                language.profile().name()
+            );
+        }
+        BookCommand::List => {
+            let current = library.current()?;
+
+            for name in library.list()? {
+                match name == current {
+                    true => println!("* {}", style(name).bold()),
+                    false => println!("  {}", name),
+                }
+            }
+        }
+        BookCommand::Switch { name } => {
+            library.switch(&name)?;
+            println!("Now using the book {}.", name);
+        }
+        BookCommand::Rename { name, new_name } => {
+            library.rename(&name, &new_name)?;
+            println!("Renamed the book {} to {}.", name, new_name);
+        }
+        BookCommand::Delete { name, yes } => {
+            let prompt = format!("Delete the book {} and all its progress?", name);
+            if yes || ui::confirm(&prompt)? {
+                library.delete(&name)?;
+                println!("Deleted the book {}.", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Load the book to work on, failing if there is none yet.
+fn load_existing_book(path: &str) -> Result<StudyBook> {
+    open_study_book(path)?.ok_or_else(|| Error::NoBook(path.to_owned()))
+}
+
+// "5 minutes ago", "2 days ago"...
+fn format_age(timestamp: u64, now: u64) -> String {
+    match now.saturating_sub(timestamp) {
+        secs if secs < 60 => String::from("just now"),
+        secs if secs < 60 * 60 => format!("{} minutes ago", secs / 60),
+        secs if secs < DAY => format!("{} hours ago", secs / 60 / 60),
+        secs => format!("{} days ago", secs / DAY),
+    }
+}
+
+// "in 3 days", "today"...
+// Counted in calendar days, overdue words being due today.
+fn format_due(entry: &WordEntry, now: u64) -> String {
+    match (entry.schedule.due / DAY).saturating_sub(now / DAY) {
+        0 => String::from("due today"),
+        1 => String::from("due tomorrow"),
+        days => format!("due in {} days", days),
+    }
+}
+
+fn format_counts(counts: &[usize]) -> String {
+    let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+    counts.join(" ")
+}
+
+pub fn run(command: Command, ctx: &Context) -> Result<()> {
+    let path = ctx.book.as_str();
+
+    match command {
+        Command::Add { file, editor } => {
+            let source = Source::from_arg(file, editor);
+            let config = ParserConfig::load_or_default(&ctx.library.parser_config_path())?;
+            let mut input = source.read(None)?;
+
+            let report = loop {
+                match update_wordbook(&input, path, config.clone()) {
+                    // Let the user fix the markups in the editor
+                    Err(Error::Markup(diagnostics)) if source == Source::Editor => {
+                        ui::show_diagnostics(&input, &diagnostics);
+                        input = source.read(Some(&input))?;
+                    }
+                    res => break res?,
+                }
+            };
+
+            ui::show_merge_report(&report);
+        }
+        Command::Study => {
+            let mut book = load_existing_book(path)?;
+            let summary = session::study(&mut book, path, &ctx.profile.settings)?;
+
+            println!(
+                "Session finished, {}: {} of {} words answered correctly.",
+                ctx.profile.name, summary.correct, summary.reviewed
+            );
+        }
+        Command::Stats { days } => {
+            let book = load_existing_book(path)?;
+            let s = book.get_status();
+            let now = schedule::now();
+
+            println!("Language:  {}", book.language.profile().name());
+            println!(
+                "Backlog:   {} words of {} sentences",
+                s.w_backlog, s.s_backlog
+            );
+            println!(
+                "Mastered:  {} words of {} sentences",
+                s.w_archived, s.s_archived
+            );
+            println!("Due today: {} words", book.due_today(now).len());
+
+            let reviews = history::load(path)?;
+            if reviews.is_empty() {
+                return Ok(());
+            }
+            let st = stats::compute(&reviews, &book, now, days.max(1));
+
+            println!();
+            println!(
+                "Reviews:   {}, {:.0}% answered correctly in {:.1}s on average",
+                st.reviews,
+                st.retention.unwrap_or_default() * 100.0,
+                st.response_ms.unwrap_or_default() as f32 / 1000.0
+            );
+            println!("Streak:    {} days, {} at best", st.streak, st.best_streak);
+            println!(
+                "Reviews per day, today last:  {}",
+                format_counts(&st.per_day)
+            );
+            println!(
+                "Due per day, today first:     {}",
+                format_counts(&st.forecast)
+            );
+
+            if !st.hardest.is_empty() {
+                println!("Hardest words:");
+            }
+            for hard in &st.hardest {
+                if let Some(e) = book.word(&hard.word_id) {
+                    println!(
+                        "  {}\t{}\t{} misses in {} reviews",
+                        e.word.word(),
+                        e.hiragana,
+                        hard.misses,
+                        hard.reviews
+                    );
+                }
+            }
+        }
+        Command::List { archived, backlog } => {
+            let book = load_existing_book(path)?;
+            let now = schedule::now();
+
+            let mut words: Vec<(&String, &WordEntry)> = book
+                .words_iter()
+                .filter(|(id, _)| match (archived, backlog) {
+                    (true, _) => book.is_archived(id),
+                    (_, true) => !book.is_archived(id),
+                    _ => true,
+                })
+                .collect();
+            words.sort_by_key(|(_, e)| (e.schedule.due, e.word.word()));
+
+            for (_, e) in words {
+                println!(
+                    "{}\t{}\tlevel {}\t{}",
+                    e.word.word(),
+                    e.hiragana,
+                    e.level,
+                    format_due(e, now)
+                );
+            }
+        }
+        Command::Export { output, format } => {
+            let book = load_existing_book(path)?;
+            let exported = match format {
+                ExportFormat::Json => {
+                    serde_json::to_string_pretty(&book).map_err(Error::Serialize)?
+                }
+                ExportFormat::Anki => book.to_anki_tsv(),
+            };
+
+            match output {
+                Some(output) => {
+                    fs::write(&output, exported).map_err(|err| Error::io(&output, err))?
+                }
+                None => print!("{}", exported),
+            }
+        }
+        Command::Import {
+            file,
+            format,
+            word,
+            reading,
+            meaning,
+            sentence,
+            no_headers,
+        } => {
+            let saved = open_study_book(path)?;
+            let format = format.unwrap_or_else(|| ImportFormat::guess(&file));
+
+            let incoming = match format {
+                // The file to import is left as it is
+                ImportFormat::Json => {
+                    load_study_book(&file)?.ok_or_else(|| Error::NoBook(file.to_owned()))?
+                }
+                ImportFormat::Csv | ImportFormat::Tsv => {
+                    let content = fs::read_to_string(&file).map_err(|err| Error::io(&file, err))?;
+                    let table = TableFormat {
+                        has_headers: !no_headers,
+                        columns: ColumnMapping {
+                            word,
+                            reading,
+                            meaning: Some(meaning),
+                            sentence: Some(sentence),
+                        },
+                        ..match format {
+                            ImportFormat::Csv => TableFormat::csv(),
+                            _ => TableFormat::tsv(),
+                        }
+                    };
+                    // The words are in the language of the book
+                    let language = match &saved {
+                        Some(book) => book.language,
+                        None => {
+                            ParserConfig::load_or_default(&ctx.library.parser_config_path())?
+                                .language
+                        }
+                    };
+                    StudyBook::from_table(&content, &table, language)?
+                }
+            };
+
+            let book = saved.unwrap_or_else(|| StudyBook {
+                language: incoming.language,
+                ..StudyBook::default()
+            });
+            let (book, report) = StudyBook::merge(book, incoming, &MergePolicy::default());
+            book.save_json(path)?;
+
+            ui::show_merge_report(&report);
+        }
+        Command::Search { query } => {
+            let book = load_existing_book(path)?;
+
+            for (_, e) in book.search(&query) {
+                let annotation = e.annotation.as_deref().unwrap_or_default();
+                println!(
+                    "{}\t{}\t{}",
+                    style(e.word.word()).bold(),
+                    e.hiragana,
+                    annotation
+                );
+
+                for s in e.sentence_ids.iter().filter_map(|id| book.sentence(id)) {
+                    println!(
+                        "\t{}",
+                        s.sentence.cloze(
+                            e.word.word(),
+                            |w| style(w).underlined().to_string(),
+                            str::to_owned
+                        )
+                    );
+                }
+            }
+        }
+        Command::Restore { backup } => {
+            let backups = backup::list(path)?;
+            let now = schedule::now();
+
+            match backup {
+                Some(n) => {
+                    let chosen = n
+                        .checked_sub(1)
+                        .and_then(|i| backups.get(i))
+                        .ok_or(Error::NoBackup(n))?;
+                    backup::restore(path, chosen)?;
+                    println!(
+                        "Restored the book as it was {}, the replaced book is the newest backup.",
+                        format_age(chosen.timestamp, now)
+                    );
+                }
+                None if backups.is_empty() => println!("The book has no backups yet."),
+                None => {
+                    for (i, b) in backups.iter().enumerate() {
+                        println!("{}\t{}", i + 1, format_age(b.timestamp, now));
+                    }
+                }
+            }
+        }
+        Command::Book { command } => run_book_command(command, &ctx.library)?,
+    }
+
+    Ok(())
+}
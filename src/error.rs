@@ -0,0 +1,156 @@
+use crate::parser::diagnostic::Diagnostic;
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum Error {
+    // Failed to read or write the file at `path`.
+    Io {
+        path: String,
+        source: io::Error,
+    },
+    // The file at `path` isn't a valid book.
+    Parse {
+        path: String,
+        source: serde_json::Error,
+    },
+    // The book at `path` was saved by a newer version of the app.
+    Version {
+        path: String,
+        version: u64,
+    },
+    // The parser config file at `path` is invalid.
+    Config {
+        path: String,
+        source: serde_json::Error,
+    },
+    // The markup settings can't make a parser.
+    Syntax(String),
+    // There is no book at `path` yet.
+    NoBook(String),
+    // The library has no book of this name.
+    UnknownBook(String),
+    // The library has a book of this name already.
+    BookExists(String),
+    // Two different words or sentences got the same id.
+    IdCollision(String, String),
+    // The vocabulary list can't be imported.
+    Table(String),
+    // The book has no backup of this number.
+    NoBackup(usize),
+    // There is no user profile of this name.
+    UnknownUser(String),
+    UserExists(String),
+    // The name of a book or user can't be a file name.
+    InvalidName(String),
+    // Failed to convert the book into json.
+    Serialize(serde_json::Error),
+    // The input content has invalid markups.
+    Markup(Vec<Diagnostic>),
+    // Failed to get the input from the user.
+    Ui(io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn io(path: &str, source: io::Error) -> Error {
+        Error::Io {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    pub fn parse(path: &str, source: serde_json::Error) -> Error {
+        Error::Parse {
+            path: path.to_owned(),
+            source,
+        }
+    }
+
+    // What the user can do about it.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Error::Io { source, .. } => match source.kind() {
+                io::ErrorKind::NotFound => Some("Check that the directory exists."),
+                io::ErrorKind::PermissionDenied => Some("Check the permissions of the file."),
+                _ => None,
+            },
+            Error::Parse { .. } => {
+                Some("Fix the file at the given position, or move it away to start a new book.")
+            }
+            Error::Version { .. } => Some("Update the app to open this book."),
+            Error::Markup(_) => Some("Markups look like <<word・reading・annotation>> by default."),
+            Error::Config { .. } => Some("Fix the config file at the given position, or remove it to use the default markups."),
+            Error::NoBook(_) => Some("Add some content to the book first, e.g. with the add command."),
+            Error::UnknownBook(_) => Some("See the books of your library with the book list command."),
+            Error::BookExists(_) => Some("Pick another name, or delete the existing book first."),
+            Error::IdCollision(..) => Some("Please report it, and add one of them in another article meanwhile."),
+            Error::Table(_) => Some("Check the columns of the words, readings, meanings and sentences, see import --help."),
+            Error::NoBackup(_) => Some("See the backups of the book with the restore command."),
+            Error::UnknownUser(_) => Some("See the users with the user list command, or create one with user create."),
+            Error::UserExists(_) => Some("Pick another name, or switch to the existing user."),
+            Error::InvalidName(_) => Some("Names can't be empty, start with a dot or contain slashes."),
+            Error::Serialize(_) | Error::Syntax(_) | Error::Ui(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "failed to access {}: {}", path, source),
+            Error::Parse { path, source } => write!(f, "{} is not a valid book: {}", path, source),
+            Error::Config { path, source } => {
+                write!(f, "{} is not a valid config: {}", path, source)
+            }
+            Error::Version { path, version } => write!(
+                f,
+                "{} is a book of format version {}, newer than the supported {}",
+                path,
+                version,
+                crate::study_book::migrate::FORMAT_VERSION
+            ),
+            Error::Syntax(msg) => write!(f, "invalid markup settings: {}", msg),
+            Error::NoBook(path) => write!(f, "there is no book at {}", path),
+            Error::UnknownBook(name) => write!(f, "there is no book named {}", name),
+            Error::BookExists(name) => write!(f, "the book {} exists already", name),
+            Error::IdCollision(a, b) => write!(f, "{} and {} have the same id", a, b),
+            Error::Table(msg) => write!(f, "invalid vocabulary list: {}", msg),
+            Error::NoBackup(n) => write!(f, "there is no backup number {}", n),
+            Error::UnknownUser(name) => write!(f, "there is no user named {}", name),
+            Error::UserExists(name) => write!(f, "the user {} exists already", name),
+            Error::InvalidName(name) => write!(f, "{:?} is not a valid name", name),
+            Error::Serialize(source) => {
+                write!(f, "failed to convert the book into json: {}", source)
+            }
+            Error::Markup(diagnostics) => {
+                let msgs: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "invalid markup {}", msgs.join("; "))
+            }
+            Error::Ui(source) => write!(f, "failed to get the input: {}", source),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } | Error::Ui(source) => Some(source),
+            Error::Parse { source, .. }
+            | Error::Config { source, .. }
+            | Error::Serialize(source) => Some(source),
+            Error::Markup(_)
+            | Error::Version { .. }
+            | Error::Syntax(_)
+            | Error::NoBook(_)
+            | Error::UnknownBook(_)
+            | Error::BookExists(_)
+            | Error::IdCollision(..)
+            | Error::Table(_)
+            | Error::NoBackup(_)
+            | Error::UnknownUser(_)
+            | Error::UserExists(_)
+            | Error::InvalidName(_) => None,
+        }
+    }
+}
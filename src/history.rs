@@ -0,0 +1,171 @@
+use crate::{
+    error::{Error, Result},
+    library::path_str,
+    session::QuizMode,
+    study_book::schedule::DAY,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+pub mod stats;
+
+const HISTORY_DIR: &str = ".history";
+
+// One answered question.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Review {
+    pub word_id: String,
+    // Unix timestamp in seconds
+    pub timestamp: u64,
+    pub mode: QuizMode,
+    pub answer: String,
+    pub correct: bool,
+    // From the question shown to the answer given
+    pub response_ms: u64,
+}
+
+// .history/<book name>.jsonl next to the book, one review per line.
+fn log_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(".jsonl");
+
+    path.with_file_name(HISTORY_DIR).join(name)
+}
+
+// Add the review at the end of the log of the book at `path`.
+pub fn append(path: &str, review: &Review) -> Result<()> {
+    let log = log_path(path);
+    let log_str = path_str(&log);
+    if let Some(dir) = log.parent() {
+        fs::create_dir_all(dir).map_err(|err| Error::io(&log_str, err))?;
+    }
+
+    let line = serde_json::to_string(review).map_err(Error::Serialize)?;
+    let write = || -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&log)?;
+
+        // Start on a new line after a line cut short
+        let mut last = [b'\n'];
+        if file.metadata()?.len() > 0 {
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+        }
+        if last[0] != b'\n' {
+            writeln!(file)?;
+        }
+
+        writeln!(file, "{}", line)
+    };
+
+    write().map_err(|err| Error::io(&log_str, err))
+}
+
+// The reviews of the book at `path`, the oldest first.
+// A line cut short, e.g. by a crash while writing it, is left out.
+pub fn load(path: &str) -> Result<Vec<Review>> {
+    let log = log_path(path);
+    let content = match fs::read_to_string(&log) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(Error::io(&path_str(&log), err)),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// The reviews of the book on the day (UTC) of `now`, in any session.
+pub fn count_on_day(path: &str, now: u64) -> Result<usize> {
+    Ok(load(path)?
+        .iter()
+        .filter(|r| r.timestamp / DAY == now / DAY)
+        .count())
+}
+
+// Move the log along with a renamed book.
+pub fn rename(path: &str, new_path: &str) -> Result<()> {
+    let (from, to) = (log_path(path), log_path(new_path));
+    if !from.is_file() {
+        return Ok(());
+    }
+
+    fs::rename(&from, &to).map_err(|err| Error::io(&path_str(&from), err))
+}
+
+// Forget the reviews of a deleted book, so a new book of the same name starts afresh.
+pub fn remove(path: &str) -> Result<()> {
+    let log = log_path(path);
+    if !log.is_file() {
+        return Ok(());
+    }
+
+    fs::remove_file(&log).map_err(|err| Error::io(&path_str(&log), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIR: &str = ".test/history";
+
+    fn review(word_id: &str, timestamp: u64, correct: bool) -> Review {
+        Review {
+            word_id: word_id.to_owned(),
+            timestamp,
+            mode: QuizMode::Reading,
+            answer: String::from("こうじょう"),
+            correct,
+            response_ms: 1500,
+        }
+    }
+
+    #[test]
+    fn appends_and_loads_reviews() {
+        let _ = fs::remove_dir_all(DIR);
+        let path = format!("{}/book.json", DIR);
+        let renamed = format!("{}/renamed.json", DIR);
+        assert_eq!(load(&path).unwrap(), vec![]);
+
+        append(&path, &review("a", 100, true)).unwrap();
+        append(&path, &review("b", 200, false)).unwrap();
+        // A line left half written
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(log_path(&path))
+            .unwrap();
+        write!(file, "{{\"word_id\":\"c\",").unwrap();
+        append(&path, &review("d", 300, true)).unwrap();
+
+        assert_eq!(
+            load(&path).unwrap(),
+            vec![
+                review("a", 100, true),
+                review("b", 200, false),
+                review("d", 300, true)
+            ]
+        );
+
+        assert_eq!(count_on_day(&path, 250).unwrap(), 3);
+        assert_eq!(count_on_day(&path, DAY + 250).unwrap(), 0);
+
+        rename(&path, &renamed).unwrap();
+        assert_eq!(load(&path).unwrap(), vec![]);
+        assert_eq!(load(&renamed).unwrap().len(), 3);
+
+        remove(&renamed).unwrap();
+        assert_eq!(load(&renamed).unwrap(), vec![]);
+
+        fs::remove_dir_all(DIR).unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use super::Review;
+use crate::study_book::{schedule::DAY, StudyBook};
+use std::collections::{BTreeSet, HashMap};
+
+// Words listed as the hardest ones.
+pub const HARDEST: usize = 5;
+
+// A word often answered wrong.
+#[derive(Debug, PartialEq)]
+pub struct Hard {
+    pub word_id: String,
+    pub misses: usize,
+    pub reviews: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub reviews: usize,
+    // Share of the reviews answered correctly, None before the first review
+    pub retention: Option<f32>,
+    // Average time taken to answer, in milliseconds
+    pub response_ms: Option<u64>,
+    // Reviews on each of the last days, today last
+    pub per_day: Vec<usize>,
+    // Days in a row with reviews, up to today, or yesterday while today is still open
+    pub streak: usize,
+    pub best_streak: usize,
+    pub hardest: Vec<Hard>,
+    // Words due on each of the next days, today first with the overdue ones
+    pub forecast: Vec<usize>,
+}
+
+// The longest run of consecutive days, and the run ending on `last`.
+fn streaks(days: &BTreeSet<u64>, last: u64) -> (usize, usize) {
+    let (mut best, mut run, mut previous) = (0, 0, None);
+
+    for day in days {
+        run = match previous {
+            Some(p) if p + 1 == *day => run + 1,
+            _ => 1,
+        };
+        best = std::cmp::max(best, run);
+        previous = Some(*day);
+    }
+
+    let current = match previous {
+        Some(p) if p == last || p + 1 == last => run,
+        _ => 0,
+    };
+
+    (current, best)
+}
+
+// The words still in the book with the most misses.
+fn hardest(reviews: &[Review], book: &StudyBook) -> Vec<Hard> {
+    let mut words: HashMap<&str, Hard> = HashMap::new();
+    for r in reviews {
+        let hard = words.entry(&r.word_id).or_insert_with(|| Hard {
+            word_id: r.word_id.to_owned(),
+            misses: 0,
+            reviews: 0,
+        });
+        hard.reviews += 1;
+        hard.misses += usize::from(!r.correct);
+    }
+
+    let mut hardest: Vec<Hard> = words
+        .into_values()
+        .filter(|h| h.misses > 0 && book.word(&h.word_id).is_some())
+        .collect();
+    // Most misses first, then the highest share of misses:
+    //  b.misses / b.reviews against a.misses / a.reviews
+    hardest.sort_by(|a, b| {
+        b.misses
+            .cmp(&a.misses)
+            .then((b.misses * a.reviews).cmp(&(a.misses * b.reviews)))
+            .then(a.word_id.cmp(&b.word_id))
+    });
+    hardest.truncate(HARDEST);
+
+    hardest
+}
+
+// Words due on each of the `days` days from `now`.
+fn forecast(book: &StudyBook, now: u64, days: usize) -> Vec<usize> {
+    let mut forecast = vec![0; days];
+    let today = now / DAY;
+
+    for (_, entry) in book.words_iter() {
+        let day = (entry.schedule.due / DAY).saturating_sub(today) as usize;
+        if let Some(count) = forecast.get_mut(day) {
+            *count += 1;
+        }
+    }
+
+    forecast
+}
+
+// Compute the numbers of the book and its reviews, over `days` days back and forth from `now`.
+// The days are UTC ones, as for the schedules.
+pub fn compute(reviews: &[Review], book: &StudyBook, now: u64, days: usize) -> Stats {
+    let today = now / DAY;
+    let correct = reviews.iter().filter(|r| r.correct).count();
+
+    let mut per_day = vec![0; days];
+    for r in reviews {
+        let ago = today.saturating_sub(r.timestamp / DAY) as usize;
+        if ago < days {
+            per_day[days - 1 - ago] += 1;
+        }
+    }
+
+    let active: BTreeSet<u64> = reviews.iter().map(|r| r.timestamp / DAY).collect();
+    let (streak, best_streak) = streaks(&active, today);
+
+    Stats {
+        reviews: reviews.len(),
+        retention: match reviews.len() {
+            0 => None,
+            n => Some(correct as f32 / n as f32),
+        },
+        response_ms: match reviews.len() {
+            0 => None,
+            n => Some(reviews.iter().map(|r| r.response_ms).sum::<u64>() / n as u64),
+        },
+        per_day,
+        streak,
+        best_streak,
+        hardest: hardest(reviews, book),
+        forecast: forecast(book, now, days),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{session::QuizMode, study_book::word::Word};
+
+    const NOW: u64 = 100 * DAY + 3600;
+    const ARTICLE: &str = "<<工場・こうじょう>>の<<稼働・かどう>>を<<停止・ていし>>";
+
+    fn review(word: &str, day: u64, correct: bool) -> Review {
+        Review {
+            word_id: Word::from(word).id(),
+            timestamp: day * DAY + 60,
+            mode: QuizMode::Reading,
+            answer: String::new(),
+            correct,
+            response_ms: 1000 + 1000 * u64::from(!correct),
+        }
+    }
+
+    #[test]
+    fn counts_streaks_and_hard_words() {
+        let reviews = vec![
+            review("停止", 89, false),
+            review("停止", 89, true),
+            review("停止", 89, true),
+            review("工場", 90, false),
+            review("工場", 91, false),
+            review("稼働", 92, true),
+            review("稼働", 93, false),
+            review("停止", 94, true),
+            review("工場", 98, true),
+            review("停止", 99, true),
+            review("停止", 99, true),
+        ];
+        let book = StudyBook::from_article(ARTICLE).unwrap();
+        let stats = compute(&reviews, &book, NOW, 3);
+
+        assert_eq!(stats.reviews, 11);
+        assert_eq!(stats.retention, Some(7.0 / 11.0));
+        assert_eq!(stats.response_ms, Some(1363));
+        assert_eq!(stats.per_day, vec![1, 2, 0]);
+        // Today is still open
+        assert_eq!(stats.streak, 2);
+        assert_eq!(stats.best_streak, 6);
+        assert_eq!(
+            stats.hardest,
+            vec![
+                Hard {
+                    word_id: Word::from("工場").id(),
+                    misses: 2,
+                    reviews: 3
+                },
+                // Missed as often as 停止, but in fewer reviews
+                Hard {
+                    word_id: Word::from("稼働").id(),
+                    misses: 1,
+                    reviews: 2
+                },
+                Hard {
+                    word_id: Word::from("停止").id(),
+                    misses: 1,
+                    reviews: 6
+                },
+            ]
+        );
+
+        let stats = compute(&reviews, &book, NOW + 2 * DAY, 3);
+        assert_eq!(stats.streak, 0);
+
+        // Words removed from the book are left out
+        let stats = compute(&reviews, &StudyBook::default(), NOW, 3);
+        assert!(stats.hardest.is_empty());
+
+        let stats = compute(&[], &book, NOW, 3);
+        assert_eq!((stats.retention, stats.response_ms), (None, None));
+    }
+
+    #[test]
+    fn forecasts_due_words() {
+        let mut book = StudyBook::from_article(ARTICLE).unwrap();
+        let due = [("工場", 0), ("稼働", NOW + DAY), ("停止", NOW + 5 * DAY)];
+        for (word, ts) in due {
+            book.word_mut(&Word::from(word).id()).unwrap().schedule.due = ts;
+        }
+
+        assert_eq!(forecast(&book, NOW, 3), vec![1, 1, 0]);
+    }
+}
@@ -1,7 +1,14 @@
-use serde::{Deserialize, Serialize};
+// 64-bit FNV-1a, stable across builds and platforms unlike the std hasher.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
 
-// #[derive(Deserialize, Serialize)]
-// #[serde(flatten)]
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// The content an id is made of, e.g. a word or a sentence.
 pub struct IdSrc {
     value: String,
     preprocessor: Option<fn(String) -> String>,
@@ -15,21 +22,28 @@ impl IdSrc {
         }
     }
 
-    pub fn from_str(value: &str) -> IdSrc {
+    pub fn new(value: &str) -> IdSrc {
         IdSrc {
             value: value.to_owned(),
             preprocessor: None,
         }
     }
 
-    pub fn id(&self) -> String {
-        let mut v = self.value.to_owned();
+    // The value, once preprocessed.
+    pub fn content(&self) -> String {
+        let v = self.value.to_owned();
 
-        if let Some(p) = self.preprocessor {
-            v = p(v);
+        match self.preprocessor {
+            Some(p) => p(v),
+            None => v,
         }
+    }
 
-        base64::encode(v)
+    // A short hash of the content, as 16 hex digits.
+    // Different contents may share an id, however unlikely, so compare the
+    //  contents before treating two entries of the same id as the same one.
+    pub fn id(&self) -> String {
+        format!("{:016x}", fnv1a(self.content().as_bytes()))
     }
 
     pub fn value(&self) -> &str {
@@ -38,15 +52,18 @@ impl IdSrc {
 }
 
 #[cfg(test)]
-
 mod tests {
     use super::*;
     use regex::Regex;
 
     #[test]
     fn word_to_id() {
-        let w = IdSrc::from_str("自動車");
-        assert_eq!(w.id(), base64::encode("自動車"));
+        let w = IdSrc::new("自動車");
+        assert_eq!(w.id().len(), 16);
+        assert_eq!(w.id(), IdSrc::new("自動車").id());
+        assert_ne!(w.id(), IdSrc::new("自動").id());
+        assert_eq!(IdSrc::new("").id(), "cbf29ce484222325");
+        assert_eq!(IdSrc::new("a").id(), "af63dc4c8601ec8c");
     }
 
     #[test]
@@ -59,6 +76,7 @@ mod tests {
             },
         );
 
-        assert_eq!(s.id(), base64::encode("トヨタ自動車はあすからロシア"));
+        assert_eq!(s.content(), "トヨタ自動車はあすからロシア");
+        assert_eq!(s.id(), IdSrc::new("トヨタ自動車はあすからロシア").id());
     }
 }
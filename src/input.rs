@@ -0,0 +1,81 @@
+use crate::{
+    error::{Error, Result},
+    ui,
+};
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+};
+
+// Where to read the marked-up content from.
+#[derive(Debug, PartialEq)]
+pub enum Source {
+    File(String),
+    Stdin,
+    Editor,
+}
+
+impl Source {
+    // Pick the source from the command line:
+    //  - "-" -> stdin
+    //  - a path -> the file
+    //  - nothing -> the piped stdin, or the editor if there is no pipe
+    pub fn from_arg(file: Option<String>, editor: bool) -> Source {
+        match (file, editor) {
+            (_, true) => Source::Editor,
+            (Some(file), _) if file == "-" => Source::Stdin,
+            (Some(file), _) => Source::File(file),
+            (None, _) if io::stdin().is_terminal() => Source::Editor,
+            (None, _) => Source::Stdin,
+        }
+    }
+
+    // Read the whole content, `default` prefills the editor.
+    pub fn read(&self, default: Option<&str>) -> Result<String> {
+        match self {
+            Source::File(path) => fs::read_to_string(path).map_err(|err| Error::io(path, err)),
+            Source::Stdin => {
+                let mut content = String::new();
+                io::stdin()
+                    .read_to_string(&mut content)
+                    .map_err(|err| Error::io("stdin", err))?;
+                Ok(content)
+            }
+            Source::Editor => ui::request_content_in_editor(default),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_pick_source_from_args() {
+        assert_eq!(
+            Source::from_arg(Some(String::from("-")), false),
+            Source::Stdin
+        );
+        assert_eq!(
+            Source::from_arg(Some(String::from("a.txt")), false),
+            Source::File(String::from("a.txt"))
+        );
+        assert_eq!(
+            Source::from_arg(Some(String::from("a.txt")), true),
+            Source::Editor
+        );
+    }
+
+    #[test]
+    fn can_read_many_lines_from_file() {
+        let content = Source::File(String::from(".test/iamhere.txt"))
+            .read(None)
+            .unwrap();
+        assert_eq!(content.trim(), "iamhere");
+
+        match Source::File(String::from(".test/ghost.txt")).read(None) {
+            Err(Error::Io { path, .. }) => assert_eq!(path, ".test/ghost.txt"),
+            _ => panic!(),
+        }
+    }
+}
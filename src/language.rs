@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub mod kana;
+
+// What differs from one language to another when studying it.
+pub trait Language {
+    fn name(&self) -> &'static str;
+
+    // What the reading field of a word holds, e.g. hiragana or pinyin.
+    fn reading_label(&self) -> &'static str;
+
+    fn sentence_terminators(&self) -> Vec<char>;
+
+    // Bring a reading into a canonical form, so equivalent answers compare equal.
+    fn normalize(&self, text: &str) -> String;
+
+    // Bring a typed answer into the canonical form of the readings,
+    //  e.g. from a transliteration easier to type.
+    fn normalize_answer(&self, answer: &str) -> String {
+        self.normalize(answer)
+    }
+
+    // How the readings can be typed, if not obvious.
+    fn input_hint(&self) -> Option<&'static str> {
+        None
+    }
+
+    // What is off in a wrong answer which is close to the reading.
+    fn near_miss(&self, _answer: &str, _reading: &str) -> Option<String> {
+        None
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    Japanese,
+    Chinese,
+    Korean,
+}
+
+impl Lang {
+    pub fn all() -> [Lang; 3] {
+        [Lang::Japanese, Lang::Chinese, Lang::Korean]
+    }
+
+    pub fn profile(&self) -> &'static dyn Language {
+        match self {
+            Lang::Japanese => &Japanese,
+            Lang::Chinese => &Chinese,
+            Lang::Korean => &Korean,
+        }
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lang::all()
+            .into_iter()
+            .find(|lang| lang.profile().name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown language {}, try japanese, chinese or korean", s))
+    }
+}
+
+// Full-width ASCII -> half-width, then lowercase without spaces.
+fn fold(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+pub struct Japanese;
+
+impl Language for Japanese {
+    fn name(&self) -> &'static str {
+        "Japanese"
+    }
+
+    fn reading_label(&self) -> &'static str {
+        "hiragana"
+    }
+
+    fn sentence_terminators(&self) -> Vec<char> {
+        vec!['。', '！', '？', '!', '?', '…']
+    }
+
+    // Katakana -> hiragana
+    fn normalize(&self, text: &str) -> String {
+        kana::katakana_to_hiragana(&fold(text))
+    }
+
+    // Romaji -> hiragana too
+    fn normalize_answer(&self, answer: &str) -> String {
+        kana::romaji_to_hiragana(&self.normalize(answer))
+    }
+
+    fn input_hint(&self) -> Option<&'static str> {
+        Some("kana or romaji")
+    }
+
+    fn near_miss(&self, answer: &str, reading: &str) -> Option<String> {
+        let answer = self.normalize_answer(answer);
+        kana::near_miss(&answer, &self.normalize(reading)).map(|m| m.to_string())
+    }
+}
+
+pub struct Chinese;
+
+impl Language for Chinese {
+    fn name(&self) -> &'static str {
+        "Chinese"
+    }
+
+    fn reading_label(&self) -> &'static str {
+        "pinyin"
+    }
+
+    fn sentence_terminators(&self) -> Vec<char> {
+        vec!['。', '！', '？', '；', '!', '?', '…']
+    }
+
+    // "Zhōng'guó" -> "zhōngguó", "lv" -> "lü"
+    fn normalize(&self, text: &str) -> String {
+        fold(text)
+            .chars()
+            .filter(|c| !matches!(c, '\'' | '’' | '-'))
+            .map(|c| match c {
+                'v' => 'ü',
+                _ => c,
+            })
+            .collect()
+    }
+}
+
+pub struct Korean;
+
+impl Language for Korean {
+    fn name(&self) -> &'static str {
+        "Korean"
+    }
+
+    fn reading_label(&self) -> &'static str {
+        "romanization"
+    }
+
+    fn sentence_terminators(&self) -> Vec<char> {
+        vec!['.', '!', '?', '…']
+    }
+
+    // "Han-guk" -> "hanguk"
+    fn normalize(&self, text: &str) -> String {
+        fold(text)
+            .chars()
+            .filter(|c| !matches!(c, '-' | '\''))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_readings() {
+        let ja = Lang::Japanese.profile();
+        assert_eq!(ja.normalize(" コウ じょう "), "こうじょう");
+        assert_eq!(ja.normalize("ＡＢＣ"), "abc");
+        // Only typed answers are taken as romaji
+        assert_eq!(ja.normalize("Kou jou"), "koujou");
+        assert_eq!(ja.normalize_answer("Kou jou"), "こうじょう");
+        assert_eq!(
+            ja.near_miss("ko jo", "こうじょう").as_deref(),
+            Some("check the long vowels")
+        );
+
+        let zh = Lang::Chinese.profile();
+        assert_eq!(zh.normalize("Zhōng'guó"), "zhōngguó");
+        assert_eq!(zh.normalize("lv xing"), "lüxing");
+
+        let ko = Lang::Korean.profile();
+        assert_eq!(ko.normalize("Han-guk"), "hanguk");
+    }
+
+    #[test]
+    fn lang_is_stored_by_name() {
+        assert_eq!(
+            serde_json::to_string(&Lang::Chinese).unwrap(),
+            r#""chinese""#
+        );
+        assert_eq!(
+            serde_json::from_str::<Lang>(r#""korean""#).unwrap(),
+            Lang::Korean
+        );
+        assert_eq!("Chinese".parse::<Lang>(), Ok(Lang::Chinese));
+        assert!("klingon".parse::<Lang>().is_err());
+    }
+}
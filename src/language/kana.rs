@@ -0,0 +1,269 @@
+use std::fmt;
+
+// Romaji -> hiragana, the longest spellings are tried first.
+#[rustfmt::skip]
+const ROMAJI: &[(&str, &str)] = &[
+    ("kya", "きゃ"), ("kyu", "きゅ"), ("kyo", "きょ"),
+    ("gya", "ぎゃ"), ("gyu", "ぎゅ"), ("gyo", "ぎょ"),
+    ("sha", "しゃ"), ("shi", "し"), ("shu", "しゅ"), ("she", "しぇ"), ("sho", "しょ"),
+    ("sya", "しゃ"), ("syu", "しゅ"), ("syo", "しょ"),
+    ("jya", "じゃ"), ("jyu", "じゅ"), ("jyo", "じょ"),
+    ("zya", "じゃ"), ("zyu", "じゅ"), ("zyo", "じょ"),
+    ("cha", "ちゃ"), ("chi", "ち"), ("chu", "ちゅ"), ("che", "ちぇ"), ("cho", "ちょ"),
+    ("tya", "ちゃ"), ("tyu", "ちゅ"), ("tyo", "ちょ"),
+    ("tsu", "つ"),
+    ("nya", "にゃ"), ("nyu", "にゅ"), ("nyo", "にょ"),
+    ("hya", "ひゃ"), ("hyu", "ひゅ"), ("hyo", "ひょ"),
+    ("bya", "びゃ"), ("byu", "びゅ"), ("byo", "びょ"),
+    ("pya", "ぴゃ"), ("pyu", "ぴゅ"), ("pyo", "ぴょ"),
+    ("mya", "みゃ"), ("myu", "みゅ"), ("myo", "みょ"),
+    ("rya", "りゃ"), ("ryu", "りゅ"), ("ryo", "りょ"),
+    ("xtu", "っ"), ("ltu", "っ"),
+    ("xya", "ゃ"), ("xyu", "ゅ"), ("xyo", "ょ"),
+    ("lya", "ゃ"), ("lyu", "ゅ"), ("lyo", "ょ"),
+    ("ka", "か"), ("ki", "き"), ("ku", "く"), ("ke", "け"), ("ko", "こ"),
+    ("ga", "が"), ("gi", "ぎ"), ("gu", "ぐ"), ("ge", "げ"), ("go", "ご"),
+    ("sa", "さ"), ("si", "し"), ("su", "す"), ("se", "せ"), ("so", "そ"),
+    ("za", "ざ"), ("zi", "じ"), ("zu", "ず"), ("ze", "ぜ"), ("zo", "ぞ"),
+    ("ja", "じゃ"), ("ji", "じ"), ("ju", "じゅ"), ("je", "じぇ"), ("jo", "じょ"),
+    ("ta", "た"), ("ti", "ち"), ("tu", "つ"), ("te", "て"), ("to", "と"),
+    ("da", "だ"), ("di", "ぢ"), ("du", "づ"), ("de", "で"), ("do", "ど"),
+    ("na", "な"), ("ni", "に"), ("nu", "ぬ"), ("ne", "ね"), ("no", "の"),
+    ("ha", "は"), ("hi", "ひ"), ("hu", "ふ"), ("he", "へ"), ("ho", "ほ"),
+    ("fa", "ふぁ"), ("fi", "ふぃ"), ("fu", "ふ"), ("fe", "ふぇ"), ("fo", "ふぉ"),
+    ("ba", "ば"), ("bi", "び"), ("bu", "ぶ"), ("be", "べ"), ("bo", "ぼ"),
+    ("pa", "ぱ"), ("pi", "ぴ"), ("pu", "ぷ"), ("pe", "ぺ"), ("po", "ぽ"),
+    ("ma", "ま"), ("mi", "み"), ("mu", "む"), ("me", "め"), ("mo", "も"),
+    ("ya", "や"), ("yu", "ゆ"), ("yo", "よ"),
+    ("ra", "ら"), ("ri", "り"), ("ru", "る"), ("re", "れ"), ("ro", "ろ"),
+    ("wa", "わ"), ("wo", "を"),
+    ("xa", "ぁ"), ("xi", "ぃ"), ("xu", "ぅ"), ("xe", "ぇ"), ("xo", "ぉ"),
+    ("la", "ぁ"), ("li", "ぃ"), ("lu", "ぅ"), ("le", "ぇ"), ("lo", "ぉ"),
+    ("a", "あ"), ("i", "い"), ("u", "う"), ("e", "え"), ("o", "お"),
+    ("-", "ー"),
+];
+
+// Long vowels written with a macron: "tōkyō" -> "toukyou"
+const MACRONS: [(char, &str); 5] = [
+    ('ā', "aa"),
+    ('ī', "ii"),
+    ('ū', "uu"),
+    ('ē', "ei"),
+    ('ō', "ou"),
+];
+
+const VOWELS: &str = "aiueo";
+
+// Type a reading in a plain terminal: "toukyou", "tōkyō" -> "とうきょう".
+// Kana are kept as they are, and so are the letters which don't spell a kana.
+pub fn romaji_to_hiragana(text: &str) -> String {
+    let chars: Vec<char> = text
+        .chars()
+        .flat_map(|c| match MACRONS.iter().find(|(m, _)| *m == c) {
+            Some((_, vowels)) => vowels.chars().collect(),
+            None => vec![c],
+        })
+        .collect();
+    let mut kana = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        // A doubled consonant is a small tsu: "kitte" -> "きって"
+        if c.is_ascii_alphabetic() && !VOWELS.contains(c) && c != 'n' && next == Some(c) {
+            kana.push('っ');
+            i += 1;
+            continue;
+        }
+
+        // A syllabic n: "kanji" -> "かんじ", "kan'i" -> "かんい",
+        //  "konnichiha" -> "こんにちは"
+        if c == 'n' {
+            let after = chars.get(i + 2).copied();
+            let len = match next {
+                None => Some(1),
+                Some('\'') => Some(2),
+                Some('n') if after.is_some_and(|a| VOWELS.contains(a) || a == 'y') => Some(1),
+                Some('n') => Some(2),
+                Some(n) if !VOWELS.contains(n) && n != 'y' => Some(1),
+                _ => None,
+            };
+
+            if let Some(len) = len {
+                kana.push('ん');
+                i += len;
+                continue;
+            }
+        }
+
+        let found = (1..=3).rev().find_map(|len| {
+            let spelling: String = chars.get(i..i + len)?.iter().collect();
+            ROMAJI
+                .iter()
+                .find(|(romaji, _)| *romaji == spelling)
+                .map(|(_, kana)| (len, kana))
+        });
+
+        match found {
+            Some((len, k)) => {
+                kana.push_str(k);
+                i += len;
+            }
+            None => {
+                kana.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    kana
+}
+
+pub fn katakana_to_hiragana(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+// A reading which is only a detail away from the right one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NearMiss {
+    // とうきょう / ときょ
+    LongVowel,
+    // がっこう / かっこう
+    Dakuten,
+    // きって / きて
+    SmallTsu,
+    // きょう / きよう
+    SmallKana,
+}
+
+impl fmt::Display for NearMiss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match self {
+            NearMiss::LongVowel => "check the long vowels",
+            NearMiss::Dakuten => "check the dakuten ゛ and handakuten ゜",
+            NearMiss::SmallTsu => "check the small っ",
+            NearMiss::SmallKana => "check the small ゃ ゅ ょ",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+// The vowel a kana ends with.
+fn vowel_of(c: char) -> Option<char> {
+    const ROWS: [(char, &str); 5] = [
+        ('a', "あかがさざただなはばぱまやらわぁゃゎ"),
+        ('i', "いきぎしじちぢにひびぴみりぃ"),
+        ('u', "うくぐすずつづぬふぶぷむゆるぅゅゔ"),
+        ('e', "えけげせぜてでねへべぺめれぇ"),
+        ('o', "おこごそぞとどのほぼぽもよろをぉょ"),
+    ];
+
+    ROWS.iter()
+        .find(|(_, row)| row.contains(c))
+        .map(|(v, _)| *v)
+}
+
+// Replace every char of `from` by the char at the same position in `to`.
+fn map_chars(text: &str, from: &str, to: &str) -> String {
+    text.chars()
+        .map(|c| match from.chars().position(|f| f == c) {
+            Some(i) => to.chars().nth(i).unwrap_or(c),
+            None => c,
+        })
+        .collect()
+}
+
+// Drop what makes a vowel long: ー, and the vowels extending the one before.
+fn shorten_vowels(text: &str) -> String {
+    let mut short = String::new();
+    let mut last: Option<char> = None;
+
+    for c in text.chars() {
+        let extends = matches!(
+            (last, c),
+            (Some(_), 'ー')
+                | (Some('a'), 'あ')
+                | (Some('i'), 'い')
+                | (Some('u'), 'う')
+                | (Some('e'), 'え' | 'い')
+                | (Some('o'), 'お' | 'う')
+        );
+
+        if !extends {
+            short.push(c);
+            last = vowel_of(c);
+        }
+    }
+
+    short
+}
+
+fn simplify(text: &str, miss: NearMiss) -> String {
+    match miss {
+        NearMiss::LongVowel => shorten_vowels(text),
+        NearMiss::Dakuten => map_chars(
+            text,
+            "がぎぐげござじずぜぞだぢづでどばびぶべぼぱぴぷぺぽゔ",
+            "かきくけこさしすせそたちつてとはひふへほはひふへほう",
+        ),
+        NearMiss::SmallTsu => text.chars().filter(|c| *c != 'っ').collect(),
+        NearMiss::SmallKana => map_chars(text, "ぁぃぅぇぉゃゅょゎ", "あいうえおやゆよわ"),
+    }
+}
+
+// Tell what is off in a wrong answer, if only one kind of detail is.
+// Both readings are expected in hiragana.
+pub fn near_miss(answer: &str, reading: &str) -> Option<NearMiss> {
+    if answer == reading || answer.is_empty() {
+        return None;
+    }
+
+    [
+        NearMiss::LongVowel,
+        NearMiss::Dakuten,
+        NearMiss::SmallTsu,
+        NearMiss::SmallKana,
+    ]
+    .into_iter()
+    .find(|miss| simplify(answer, *miss) == simplify(reading, *miss))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_romaji() {
+        assert_eq!(romaji_to_hiragana("toukyou"), "とうきょう");
+        assert_eq!(romaji_to_hiragana("tōkyō"), "とうきょう");
+        assert_eq!(romaji_to_hiragana("kitte"), "きって");
+        assert_eq!(romaji_to_hiragana("kanji"), "かんじ");
+        assert_eq!(romaji_to_hiragana("kan'i"), "かんい");
+        assert_eq!(romaji_to_hiragana("konnichiha"), "こんにちは");
+        assert_eq!(romaji_to_hiragana("shinbun"), "しんぶん");
+        assert_eq!(romaji_to_hiragana("ra-men"), "らーめん");
+        assert_eq!(romaji_to_hiragana("こうjou"), "こうじょう");
+        assert_eq!(romaji_to_hiragana("cd"), "cd");
+    }
+
+    #[test]
+    fn reports_near_misses() {
+        assert_eq!(near_miss("ときょ", "とうきょう"), Some(NearMiss::LongVowel));
+        assert_eq!(near_miss("らめん", "らーめん"), Some(NearMiss::LongVowel));
+        assert_eq!(near_miss("かっこう", "がっこう"), Some(NearMiss::Dakuten));
+        assert_eq!(near_miss("はん", "ぱん"), Some(NearMiss::Dakuten));
+        assert_eq!(near_miss("きて", "きって"), Some(NearMiss::SmallTsu));
+        assert_eq!(near_miss("きよう", "きょう"), Some(NearMiss::SmallKana));
+
+        assert_eq!(near_miss("とうきょう", "とうきょう"), None);
+        assert_eq!(near_miss("おおさか", "とうきょう"), None);
+        assert_eq!(near_miss("", "とうきょう"), None);
+    }
+}
@@ -1,47 +1,101 @@
-mod parser;
+pub mod backup;
+pub mod error;
+pub mod history;
+pub mod id_source;
+pub mod input;
+pub mod language;
+pub mod library;
+pub mod parser;
+pub mod profile;
+pub mod session;
 pub mod study_book;
 pub mod ui;
 
-use serde::{Deserialize, Serialize};
-use study_book::StudyBook;
+use error::{Error, Result};
+use parser::{config::ParserConfig, Parser};
+use serde_json::Value;
+use study_book::{
+    merge::{MergePolicy, MergeReport},
+    migrate, schedule, StudyBook,
+};
 
 use std::{fs, io::ErrorKind};
 
-pub fn update_wordbook(input: &str, path: &str) {
+// Merge the marked-up input into the book saved at `path`.
+// The book is created in the language of the config if it doesn't exist yet.
+pub fn update_wordbook(input: &str, path: &str, mut config: ParserConfig) -> Result<MergeReport> {
+    let saved = open_study_book(path)?;
+
+    // A saved book keeps its own language
+    if let Some(book) = &saved {
+        config.language = book.language;
+    }
+
     // Generate book from input content.
-    let b = study_book::StudyBook::from_article(input);
-
-    //
-    match fs::read_to_string(path) {
-        Ok(content) => {
-            // convert content to book
-            // merge 2 books
-            // save merged book
-        }
-        _ => {
-            fs::write(path, "hi").unwrap();
-        }
+    let b = StudyBook::from_article_with(input, &Parser::with_config(config)?)?;
+
+    // Merge it into the saved book, if any
+    let saved = saved.unwrap_or_else(|| StudyBook {
+        language: b.language,
+        ..StudyBook::default()
+    });
+    let (book, report) = StudyBook::merge(saved, b, &MergePolicy::default());
+
+    book.save_json(path)?;
+
+    Ok(report)
+}
+
+// Load the book saved at `path`, upgrading it from an older layout if needed.
+// The file is left as it is, e.g. for a book to import or a backup.
+pub fn load_study_book(path: &str) -> Result<Option<StudyBook>> {
+    Ok(read_study_book(path)?.map(|(book, _)| book))
+}
+
+// Load the book saved at `path` to work on it.
+// A book of an older layout is saved back upgraded, the old file being kept as a backup,
+//  so it isn't upgraded again on every load.
+pub fn open_study_book(path: &str) -> Result<Option<StudyBook>> {
+    let (book, version) = match read_study_book(path)? {
+        Some(read) => read,
+        None => return Ok(None),
+    };
+
+    if version < migrate::FORMAT_VERSION {
+        backup::back_up(path, schedule::now(), true)?;
+        backup::write_atomic(path, &book.to_json()?)?;
     }
+
+    Ok(Some(book))
 }
 
-pub fn load_study_book(path: &str) -> Result<Option<StudyBook>, &'static str> {
-    let res = match fs::read_to_string(path) {
-        Ok(str) => match serde_json::from_str(&str) {
-            Ok(book) => Ok(Some(book)),
-            Err(_) => Err("The source file is invalid."),
-        },
+// The book saved at `path`, along with the version of the layout it was saved in.
+fn read_study_book(path: &str) -> Result<Option<(StudyBook, u64)>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
         Err(err) => match err.kind() {
-            ErrorKind::NotFound => Ok(None),
-            _ => Err("Failed to load the source file."),
+            ErrorKind::NotFound => return Ok(None),
+            _ => return Err(Error::io(path, err)),
         },
     };
 
-    res
+    let value: Value = serde_json::from_str(&content).map_err(|err| Error::parse(path, err))?;
+    let version = migrate::version_of(&value);
+    let value = migrate::upgrade(value).map_err(|version| Error::Version {
+        path: path.to_owned(),
+        version,
+    })?;
+
+    match serde_json::from_value(value) {
+        Ok(book) => Ok(Some((book, version))),
+        Err(err) => Err(Error::parse(path, err)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use study_book::{sentence::Sentence, word::Word};
 
     const FILE_NOT_EXIST: &str = ".test/ghost.json";
     const I_AM_HERE_TXT: &str = ".test/iamhere.txt";
@@ -49,17 +103,92 @@ mod tests {
 
     #[test]
     fn can_check_the_absence_of_book() {
-        if let Some(_) = load_study_book(FILE_NOT_EXIST).unwrap() {
+        if load_study_book(FILE_NOT_EXIST).unwrap().is_some() {
             panic!();
         }
         // assert_eq!(load_study_book(FILE_NOT_EXIST).unwrap(), None);
     }
 
+    #[test]
+    fn can_update_wordbook() {
+        let dir = ".test/update_wordbook";
+        let _ = fs::remove_dir_all(dir);
+        let path = &format!("{}/book.json", dir);
+
+        let u = update_wordbook(
+            "ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>。",
+            path,
+            ParserConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(u.added.w_backlog, 2);
+        assert_eq!(u.added.s_backlog, 1);
+
+        // Only the new word of the known sentence is counted
+        let u = update_wordbook(
+            "ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>。日本の<<自動車・じどうしゃ>>。",
+            path,
+            ParserConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(u.added.w_backlog, 1);
+        assert_eq!(u.added.s_backlog, 1);
+        assert_eq!(u.total.w_backlog, 3);
+        assert_eq!(u.total.s_backlog, 2);
+
+        let b = load_study_book(path).unwrap().unwrap();
+        assert_eq!(b.get_status(), u.total);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saves_back_the_upgraded_book() {
+        let dir = ".test/open_study_book";
+        let _ = fs::remove_dir_all(dir);
+        let path = &format!("{}/book.json", dir);
+        fs::create_dir_all(dir).unwrap();
+        fs::copy(VALID_BOOK_JSON, path).unwrap();
+
+        let book = open_study_book(path).unwrap().unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(migrate::version_of(&saved), migrate::FORMAT_VERSION);
+        assert_eq!(saved, serde_json::to_value(&book).unwrap());
+
+        // The old layout is kept as a backup, once
+        let backups = backup::list(path).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            fs::read_to_string(&backups[0].path).unwrap(),
+            fs::read_to_string(VALID_BOOK_JSON).unwrap()
+        );
+        open_study_book(path).unwrap().unwrap();
+        assert_eq!(backup::list(path).unwrap().len(), 1);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn can_reject_invalid_book() {
+        match load_study_book(I_AM_HERE_TXT) {
+            Err(err @ Error::Parse { .. }) => {
+                let msg = err.to_string();
+                assert!(msg.contains(I_AM_HERE_TXT));
+                assert!(msg.contains("line 1 column 1"));
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn can_load_the_saved_book() {
         let b = load_study_book(VALID_BOOK_JSON).unwrap().unwrap();
+        let s_id = Sentence::from(
+            "ロシアへの経済制裁が強まる中、日本の自動車メーカーに影響が広がっています。",
+        )
+        .id();
 
-        if let Some(_) = b.sentences.achived {
+        if b.sentences.achived.is_some() {
             panic!();
         }
 
@@ -67,7 +196,7 @@ mod tests {
             b.sentences
                 .backlog
                 .unwrap()
-                .get("1")
+                .get(&s_id)
                 .unwrap()
                 .sentence
                 .sentence(),
@@ -78,12 +207,20 @@ mod tests {
             b.words
                 .backlog
                 .unwrap()
-                .get("122")
+                .get(&Word::from("広がっています").id())
                 .unwrap()
                 .annotation
                 .as_ref()
                 .unwrap(),
             "to spread out"
         );
+
+        // Books saved before scheduling still load, with a fresh schedule
+        let b = load_study_book(VALID_BOOK_JSON).unwrap().unwrap();
+        let w_id = Word::from("経済制裁").id();
+        assert!(b.word(&w_id).unwrap().schedule.is_due(0));
+
+        // ...and their single sentence_id becomes a list of sentences
+        assert_eq!(b.word(&w_id).unwrap().sentence_ids, vec![s_id]);
     }
 }
@@ -0,0 +1,337 @@
+use crate::{
+    backup,
+    error::{Error, Result},
+    history,
+    language::Lang,
+    open_study_book,
+    study_book::StudyBook,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_BOOK: &str = "default";
+
+const BOOKS_DIR: &str = "books";
+const STATE_FILE: &str = "library.json";
+// Optional markup settings, see ParserConfig.
+const PARSER_CONFIG_FILE: &str = "parser.json";
+
+// The name picked last, e.g. of the book in use.
+#[derive(Serialize, Deserialize, Default)]
+struct Current {
+    current: Option<String>,
+}
+
+pub(crate) fn load_current(path: &str) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str::<Current>(&content)
+            .map(|c| c.current)
+            .map_err(|err| Error::parse(path, err)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::io(path, err)),
+    }
+}
+
+pub(crate) fn save_current(path: &str, current: Option<&str>) -> Result<()> {
+    let current = Current {
+        current: current.map(str::to_owned),
+    };
+    let json = serde_json::to_string(&current).map_err(Error::Serialize)?;
+
+    fs::write(path, json).map_err(|err| Error::io(path, err))
+}
+
+// Names of books and users are used as file names.
+pub fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !name.chars().any(char::is_control);
+
+    match valid {
+        true => Ok(()),
+        false => Err(Error::InvalidName(name.to_owned())),
+    }
+}
+
+// Named books stored as json files under a root directory.
+pub struct Library {
+    root: PathBuf,
+}
+
+// $XDG_DATA_HOME/clibook, or ~/.local/share/clibook
+pub fn default_root() -> PathBuf {
+    match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("clibook"),
+        None => PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".local/share/clibook"),
+    }
+}
+
+pub(crate) fn path_str(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+impl Library {
+    // Open the library at `root`, creating its directories if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Library> {
+        let root = root.into();
+        let books = root.join(BOOKS_DIR);
+        fs::create_dir_all(&books).map_err(|err| Error::io(&path_str(&books), err))?;
+
+        Ok(Library { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Path of a file stored alongside the books, e.g. the parser config.
+    pub fn file_path(&self, file: &str) -> String {
+        path_str(&self.root.join(file))
+    }
+
+    pub fn parser_config_path(&self) -> String {
+        self.file_path(PARSER_CONFIG_FILE)
+    }
+
+    // Move the books and settings of the library at `from` into this one, if it has no books yet.
+    pub fn adopt(&self, from: &Path) -> Result<bool> {
+        let books = from.join(BOOKS_DIR);
+        if !books.is_dir() || !self.list()?.is_empty() {
+            return Ok(false);
+        }
+
+        let own_books = self.root.join(BOOKS_DIR);
+        fs::remove_dir(&own_books).map_err(|err| Error::io(&path_str(&own_books), err))?;
+        fs::rename(&books, &own_books).map_err(|err| Error::io(&path_str(&books), err))?;
+
+        for file in [STATE_FILE, PARSER_CONFIG_FILE] {
+            let path = from.join(file);
+            if path.exists() {
+                fs::rename(&path, self.root.join(file))
+                    .map_err(|err| Error::io(&path_str(&path), err))?;
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Move the book file at `from` into this library as the default book, if it has no books yet.
+    pub fn adopt_book(&self, from: &Path) -> Result<bool> {
+        if !from.is_file() || !self.list()?.is_empty() {
+            return Ok(false);
+        }
+
+        let path = self.book_path(DEFAULT_BOOK);
+        fs::rename(from, &path).map_err(|err| Error::io(&path_str(from), err))?;
+
+        Ok(true)
+    }
+
+    pub fn book_path(&self, name: &str) -> String {
+        path_str(&self.root.join(BOOKS_DIR).join(format!("{}.json", name)))
+    }
+
+    // Names of the books, sorted.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let dir = self.root.join(BOOKS_DIR);
+        let entries = fs::read_dir(&dir).map_err(|err| Error::io(&path_str(&dir), err))?;
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                path.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    // A name which isn't a valid one, e.g. "../x", is never there.
+    pub fn contains(&self, name: &str) -> bool {
+        check_name(name).is_ok() && Path::new(&self.book_path(name)).exists()
+    }
+
+    // The book in use: the one switched to last, the only one, or the default one.
+    pub fn current(&self) -> Result<String> {
+        let current = load_current(&self.file_path(STATE_FILE))?;
+        if let Some(name) = current.filter(|name| self.contains(name)) {
+            return Ok(name);
+        }
+
+        let names = self.list()?;
+        match names.len() {
+            1 => Ok(names[0].to_owned()),
+            _ => Ok(String::from(DEFAULT_BOOK)),
+        }
+    }
+
+    fn check_exists(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        match self.contains(name) {
+            true => Ok(()),
+            false => Err(Error::UnknownBook(name.to_owned())),
+        }
+    }
+
+    // Create an empty book and switch to it.
+    pub fn create(&self, name: &str, language: Lang) -> Result<()> {
+        check_name(name)?;
+        if self.contains(name) {
+            return Err(Error::BookExists(name.to_owned()));
+        }
+
+        let book = StudyBook {
+            language,
+            ..StudyBook::default()
+        };
+        book.save_json(&self.book_path(name))?;
+
+        self.switch(name)
+    }
+
+    pub fn load(&self, name: &str) -> Result<StudyBook> {
+        self.check_exists(name)?;
+        let path = self.book_path(name);
+
+        open_study_book(&path)?.ok_or(Error::UnknownBook(name.to_owned()))
+    }
+
+    pub fn switch(&self, name: &str) -> Result<()> {
+        self.check_exists(name)?;
+
+        save_current(&self.file_path(STATE_FILE), Some(name))
+    }
+
+    pub fn rename(&self, name: &str, new_name: &str) -> Result<()> {
+        check_name(new_name)?;
+        self.check_exists(name)?;
+        if self.contains(new_name) {
+            return Err(Error::BookExists(new_name.to_owned()));
+        }
+
+        let (from, to) = (self.book_path(name), self.book_path(new_name));
+        fs::rename(&from, &to).map_err(|err| Error::io(&from, err))?;
+        backup::rename(&from, &to)?;
+        history::rename(&from, &to)?;
+
+        // Keep the renamed book in use
+        let state = self.file_path(STATE_FILE);
+        if load_current(&state)?.as_deref() == Some(name) {
+            save_current(&state, Some(new_name))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.check_exists(name)?;
+
+        let path = self.book_path(name);
+        fs::remove_file(&path).map_err(|err| Error::io(&path, err))?;
+        backup::remove(&path)?;
+        history::remove(&path)?;
+
+        let state = self.file_path(STATE_FILE);
+        if load_current(&state)?.as_deref() == Some(name) {
+            save_current(&state, None)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every test works on its own library.
+    fn open_empty(name: &str) -> Library {
+        let root = format!(".test/library_{}", name);
+        let _ = fs::remove_dir_all(&root);
+
+        Library::open(root).unwrap()
+    }
+
+    #[test]
+    fn can_manage_books() {
+        let l = open_empty("manage");
+        assert!(l.list().unwrap().is_empty());
+        assert_eq!(l.current().unwrap(), DEFAULT_BOOK);
+
+        l.create("日本語", Lang::Japanese).unwrap();
+        l.create("chinese", Lang::Chinese).unwrap();
+        assert_eq!(l.list().unwrap(), vec!["chinese", "日本語"]);
+        assert_eq!(l.current().unwrap(), "chinese");
+        assert_eq!(l.load("chinese").unwrap().language, Lang::Chinese);
+
+        l.switch("日本語").unwrap();
+        assert_eq!(l.current().unwrap(), "日本語");
+
+        l.rename("日本語", "japanese").unwrap();
+        assert_eq!(l.current().unwrap(), "japanese");
+        assert_eq!(l.list().unwrap(), vec!["chinese", "japanese"]);
+
+        // The backups go with the deleted book
+        let path = l.book_path("japanese");
+        backup::back_up(&path, 100, true).unwrap();
+        l.delete("japanese").unwrap();
+        assert_eq!(l.list().unwrap(), vec!["chinese"]);
+        assert!(backup::list(&path).unwrap().is_empty());
+        assert_eq!(l.current().unwrap(), "chinese");
+
+        fs::remove_dir_all(l.root()).unwrap();
+    }
+
+    #[test]
+    fn can_adopt_a_book_file_once() {
+        let l = open_empty("adopt_book");
+        let file = l.root().join("book.json");
+        fs::copy(".test/study_book.json", &file).unwrap();
+
+        assert!(l.adopt_book(&file).unwrap());
+        assert!(!file.exists());
+        assert_eq!(l.list().unwrap(), vec![DEFAULT_BOOK]);
+
+        // Nothing left to adopt once the book is deleted
+        l.delete(DEFAULT_BOOK).unwrap();
+        assert!(!l.adopt_book(&file).unwrap());
+        assert!(l.list().unwrap().is_empty());
+
+        fs::remove_dir_all(l.root()).unwrap();
+    }
+
+    #[test]
+    fn can_reject_invalid_operations() {
+        let l = open_empty("invalid");
+        l.create("a", Lang::Japanese).unwrap();
+
+        assert!(matches!(
+            l.create("a", Lang::Korean),
+            Err(Error::BookExists(_))
+        ));
+        assert!(matches!(
+            l.create("../a", Lang::Korean),
+            Err(Error::InvalidName(_))
+        ));
+        assert!(matches!(l.switch("b"), Err(Error::UnknownBook(_))));
+        assert!(matches!(l.delete("b"), Err(Error::UnknownBook(_))));
+        assert!(matches!(
+            l.delete("../library_invalid/books/a"),
+            Err(Error::InvalidName(_))
+        ));
+
+        l.create("b", Lang::Japanese).unwrap();
+        assert!(matches!(l.rename("a", "b"), Err(Error::BookExists(_))));
+
+        fs::remove_dir_all(l.root()).unwrap();
+    }
+}
@@ -1,71 +1,122 @@
-use console::Term;
-use dialoguer::{theme::ColorfulTheme, Input, Select};
-use std::{
-    fs,
-    io::{self, ErrorKind},
-    process,
-};
+mod cli;
+
+use clap::Parser as _;
+use cli::{Cli, Context, TopCommand};
+use std::{path::PathBuf, process};
 
 use learn_jp::{
-    load_study_book,
-    study_book::{self, status::Status, StudyBook},
-    ui::{self, NextStep},
-    update_wordbook,
+    error::{Error, Result},
+    input::Source,
+    library::{self, check_name, Library},
+    open_study_book,
+    parser::{config::ParserConfig, Parser},
+    profile::{Profile, Users, DEFAULT_USER},
+    session,
+    study_book::{merge::MergePolicy, schedule, StudyBook},
+    ui::{self, InputMethod, NextStep},
 };
 
-// <todo> Introduce the concept of user to bring some customization.
-const USER_NAME: &str = "Yan";
+// Print the error with a hint on how to fix it, then end the program.
+fn exit_with(err: Error) -> ! {
+    eprintln!("Error: {}.", err);
+    if let Some(hint) = err.hint() {
+        eprintln!("{}", hint);
+    }
+    process::exit(1);
+}
 
-const SAVE_PATH: &str = ".prod/book.json";
+// Ask for content until its markups are valid, and build a book from it.
+fn request_book(parser: &Parser) -> StudyBook {
+    let method = ui::choose_input_method().unwrap_or_else(|err| exit_with(err));
+    let mut last_input: Option<String> = None;
+
+    loop {
+        let input = match method {
+            InputMethod::Prompt => ui::request_raw_content(last_input.as_deref()),
+            InputMethod::Editor => ui::request_content_in_editor(last_input.as_deref()),
+            InputMethod::File => {
+                ui::request_file_path().and_then(|path| Source::File(path).read(None))
+            }
+        };
+
+        let input = match input {
+            Ok(input) => input,
+            // Ask for another file
+            Err(err @ Error::Io { .. }) => {
+                println!("{}", err);
+                continue;
+            }
+            Err(err) => exit_with(err),
+        };
+
+        match StudyBook::from_article_with(&input, parser) {
+            Ok(book) => return book,
+            Err(Error::Markup(diagnostics)) => {
+                ui::show_diagnostics(&input, &diagnostics);
+                last_input = Some(input);
+            }
+            Err(err) => exit_with(err),
+        }
+    }
+}
+
+// The default flow: add contents until the user is ready, then study.
+fn interactive(ctx: &Context) {
+    let path = ctx.book.as_str();
+    let name = ctx.profile.name.as_str();
+    let mut config = ParserConfig::load_or_default(&ctx.library.parser_config_path())
+        .unwrap_or_else(|err| exit_with(err));
+
+    // Handle error:
+    //  - Print the error msg
+    //  - End the thread
+    let saved = open_study_book(path).unwrap_or_else(|err| exit_with(err));
+
+    // A saved book keeps its own language
+    if let Some(book) = &saved {
+        config.language = book.language;
+    }
+
+    let parser = Parser::with_config(config).unwrap_or_else(|err| exit_with(err));
 
-fn main() {
     // Initialize study_book with either saved book or user's first input,
     //  to get a book with words in the backlog
-    let mut b = match load_study_book(SAVE_PATH) {
-        Ok(book_opt) => match book_opt {
-            // Find saved book -> Check whether there are words in the backlog
-            Some(book) => {
-                // If have no words in the backlog -> Ask for input
-                if book.no_words_in_backlog() {
-                    println!("Good job! There is no words in your backlog. Now let's add more.");
-                    let input: String = ui::request_raw_content().unwrap();
-
-                    study_book::StudyBook::from_article(&input)
-                }
-                // Else, return the book directly
-                else {
-                    book
-                }
+    let mut b = match saved {
+        // Find saved book -> Check whether there are words in the backlog
+        Some(book) => {
+            println!("Welcome back, {}.", name);
+            // If have no words in the backlog -> Ask for input, keeping the mastered words
+            if book.no_words_in_backlog() {
+                println!("Good job! There is no words in your backlog. Now let's add more.");
+                StudyBook::merge(book, request_book(&parser), &MergePolicy::default()).0
             }
-            // There is no saved book -> ask for initial input
-            None => {
-                println!("Welcome. To start the advanture, let's add some words into the backlog.");
-                let input: String = ui::request_raw_content().unwrap();
-
-                study_book::StudyBook::from_article(&input)
+            // Else, return the book directly
+            else {
+                book
             }
-        },
-        Err(err) => {
-            // Handle error:
-            //  - Print the error msg
-            //  - End the thread
-            println!("Oops something went wrong: {}.", err);
-            process::exit(1);
+        }
+        // There is no saved book -> ask for initial input
+        None => {
+            println!(
+                "Welcome, {}. To start the advanture of learning {}, let's add some words into the backlog.",
+                name,
+                parser.language().profile().name()
+            );
+            request_book(&parser)
         }
     };
 
     loop {
         // Save
-        if let Err(err) = b.save_json(SAVE_PATH) {
-            println!("Oops something went wrong: {}.", err);
-            process::exit(1);
+        if let Err(err) = b.save_json(path) {
+            exit_with(err);
         };
 
         // Report the initial status
         let s = b.get_status();
         println!(
-            "Now we have {} words of {} sentences to work on.",
-            s.w_backlog, s.s_backlog
+            "Now we have {} words of {} sentences to work on, and {} words mastered.",
+            s.w_backlog, s.s_backlog, s.w_archived
         );
 
         // Ask for the next step
@@ -76,19 +127,11 @@ fn main() {
                 // to fix: here should be a loop for users to keep adding contents
                 match decision {
                     NextStep::AddMore => {
-                        // Add more
-                        let input: String = ui::request_raw_content().unwrap();
-                        // Merge
-                        b = StudyBook::merge(
-                            b,
-                            StudyBook::from_article(&input),
-                            Some(|s_add: Status, _| {
-                                println!(
-                                    "You have just added {} words of {} new sentences.",
-                                    s_add.w_backlog, s_add.s_backlog
-                                )
-                            }),
-                        );
+                        // Add more, and merge
+                        let (book, report) =
+                            StudyBook::merge(b, request_book(&parser), &MergePolicy::default());
+                        ui::show_merge_report(&report);
+                        b = book;
                         // and then repeat the loop
                     }
                     NextStep::Study => {
@@ -96,14 +139,31 @@ fn main() {
                     }
                 }
             }
-            Err(err) => {
-                println!("Oops something went wrong: {}.", err);
-                process::exit(1);
-            }
+            Err(err) => exit_with(err),
         }
     }
 
     // Now book is ready, let's study!
+    let due = b.due_today(schedule::now()).len();
+    if due == 0 {
+        println!("Nothing is due today, {}. See you tomorrow!", name);
+        return;
+    }
+
+    println!(
+        "Let's go, {}! {} words are due today. Answer each question, or type {} (or press Esc on a choice) to stop.",
+        name,
+        due,
+        session::QUIT
+    );
+
+    match session::study(&mut b, path, &ctx.profile.settings) {
+        Ok(summary) => println!(
+            "Session finished: {} of {} words answered correctly.",
+            summary.correct, summary.reviewed
+        ),
+        Err(err) => exit_with(err),
+    }
 
     // // Initial check on whether we've got saved book...
     // match fs::read_to_string(data_file) {
@@ -154,3 +214,97 @@ fn main() {
     //      1. No file -> Create file first
     //      2. Save as json file
 }
+
+// Find the book to use: the --book file or name, or the current book of the library.
+//  When asked, let the user pick one if there are several.
+fn resolve_book(library: &Library, book: Option<&str>, prompt: bool) -> Result<String> {
+    match book {
+        Some(file) if cli::is_book_file(file) => return Ok(file.to_owned()),
+        Some(name) => {
+            check_name(name)?;
+            return Ok(library.book_path(name));
+        }
+        None => {}
+    }
+
+    let current = library.current()?;
+    let names = library.list()?;
+    if !prompt || names.len() < 2 {
+        return Ok(library.book_path(&current));
+    }
+
+    let name = ui::choose_name("Which book do you want to open?", &names, Some(&current))?;
+    library.switch(&name)?;
+
+    Ok(library.book_path(&name))
+}
+
+// Find the user: the --user one, the current one, or a new one.
+//  When asked, let the user pick one if there are several, or type their name if there are none.
+fn resolve_user(users: &Users, user: Option<&str>, prompt: bool) -> Result<Profile> {
+    if let Some(name) = user {
+        return users.load(name);
+    }
+
+    let names = users.list()?;
+    let current = users.current()?;
+    let name = match (prompt, names.len(), current) {
+        (true, 0, _) => return users.create(&ui::request_name()?),
+        (false, 0, _) => return users.create(DEFAULT_USER),
+        (true, 2.., current) => {
+            let name = ui::choose_name("Who is studying?", &names, current.as_deref())?;
+            users.switch(&name)?;
+            name
+        }
+        (_, _, Some(current)) => current,
+        (_, _, None) => names[0].to_owned(),
+    };
+
+    users.load(&name)
+}
+
+// Pick the user and the book to work on, asking for them in the interactive flow.
+fn open_context(users: &Users, cli: &Cli, prompt: bool) -> Result<Context> {
+    let profile = resolve_user(users, cli.user.as_deref(), prompt)?;
+    let library = profile.library()?;
+    let book = resolve_book(&library, cli.book.as_deref(), prompt)?;
+
+    Ok(Context {
+        profile,
+        library,
+        book,
+    })
+}
+
+fn main() {
+    let mut cli = Cli::parse();
+
+    let root = cli
+        .library
+        .as_deref()
+        .map_or_else(library::default_root, PathBuf::from);
+    let users = Users::open(root).unwrap_or_else(|err| exit_with(err));
+
+    // User commands don't need a user to be picked first
+    let command = match cli.command.take() {
+        Some(TopCommand::User { command }) => {
+            if let Err(err) = cli::run_user_command(command, &users, cli.user.as_deref()) {
+                exit_with(err);
+            }
+            return;
+        }
+        Some(TopCommand::Book(command)) => Some(command),
+        None => None,
+    };
+
+    let ctx = open_context(&users, &cli, command.is_none()).unwrap_or_else(|err| exit_with(err));
+
+    match command {
+        Some(command) => {
+            if let Err(err) = cli::run(command, &ctx) {
+                exit_with(err);
+            }
+        }
+        None => interactive(&ctx),
+    }
+}
@@ -1,43 +1,103 @@
+use crate::{error::Result, language::Lang};
+use config::{ParserConfig, Syntax};
+use diagnostic::{Diagnostic, DiagnosticKind};
 use regex::Regex;
 
+pub mod config;
+pub mod diagnostic;
+
+// Sentences don't end inside these brackets.
+const QUOTES: [(char, char); 7] = [
+    ('「', '」'),
+    ('『', '』'),
+    ('（', '）'),
+    ('(', ')'),
+    ('【', '】'),
+    ('“', '”'),
+    ('〈', '〉'),
+];
+
+// Chars making the word of a ruby markup.
+const KANJI: &str = r"\p{Han}々〆ヶ";
+
 pub struct Parser {
     cap_word: String,
     cap_entry: String,
-    delimiter_field: char,
-    delimiter_sentence: char,
+    config: ParserConfig,
+    terminators: Vec<char>,
     regex: Regex,
+    // Matches the kanji at the end of a text, for ruby markups.
+    regex_kanji: Regex,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser::new()
+    }
 }
 
 impl Parser {
     pub fn new() -> Parser {
+        Parser::with_config(ParserConfig::default()).unwrap()
+    }
+
+    // Build the parser with the markup settings of the config.
+    pub fn with_config(config: ParserConfig) -> Result<Parser> {
+        config.check()?;
+
         let cap_word = String::from("word");
         let cap_entry = String::from("entry");
-        let regex = Regex::new(&format!(
-            r"<<(?P<{}>(?P<{}>[^>・]*)[^>]*)>>",
-            cap_entry, cap_word
-        ))
-        .unwrap();
 
-        Parser {
+        let open = regex::escape(&config.bracket_open);
+        let close = regex::escape(&config.bracket_close);
+        // The first char of the closing bracket ends the markup
+        let close_first = regex::escape(&config.bracket_close.chars().take(1).collect::<String>());
+        let delimiter = regex::escape(&config.delimiter_field.to_string());
+
+        let pattern = match config.syntax {
+            // <<(?P<entry>(?P<word>[^>・]*)[^>]*)>>
+            Syntax::Bracket => format!(
+                r"{open}(?P<{}>(?P<{}>[^{close_first}{delimiter}]*)[^{close_first}]*){close}",
+                cap_entry, cap_word
+            ),
+            // (?P<entry>(?P<word>[kanji]+)\([^)]*\))
+            Syntax::Ruby => format!(
+                r"(?P<{}>(?P<{}>[{KANJI}]+){open}[^{close_first}]*{close})",
+                cap_entry, cap_word
+            ),
+        };
+
+        Ok(Parser {
             cap_word,
             cap_entry,
-            delimiter_field: '・',
-            delimiter_sentence: '。',
-            regex,
-        }
+            terminators: config.terminators(),
+            regex: Regex::new(&pattern).unwrap(),
+            regex_kanji: Regex::new(&format!("[{KANJI}]+$")).unwrap(),
+            config,
+        })
+    }
+
+    pub fn language(&self) -> Lang {
+        self.config.language
     }
 
     // "...<<word_1・rest_1>>...<<word_2>>..."
     //  -> "...`word_1`...`word_2`...。"
+    // The sentence keeps its own terminator, the default one is added only if it has none.
     pub fn clean_sentence(&self, sentence: &str) -> String {
         let mut cleaned = self
             .regex
             .replace_all(sentence, format!("`${}`", self.cap_word))
             .into_owned();
 
-        cleaned.push(self.delimiter_sentence);
-        // if &cleaned[cleaned.len() - 3..] != self.delimiter_sentence.to_string() {
-        // }
+        let terminated = cleaned
+            .chars()
+            .last()
+            .is_some_and(|c| self.terminators.contains(&c) || QUOTES.iter().any(|q| q.1 == c));
+
+        if !terminated {
+            cleaned.push(self.terminators[0]);
+        }
 
         cleaned
     }
@@ -50,18 +110,179 @@ impl Parser {
             .map(|caps| caps.name(&self.cap_entry).unwrap().as_str())
     }
 
+    // "一文目。「二文目？まだ二文目！」と言った……\n三文目"
+    //  -> iter["一文目。", "「二文目？まだ二文目！」と言った……", "三文目"]
+    // Split after each run of terminators and at line breaks,
+    // but never inside quotes, brackets or markups.
     pub fn cap_sentences_iter<'a>(&self, article: &'a str) -> impl Iterator<Item = &'a str> {
-        article
-            .split(self.delimiter_sentence)
-            .filter(|res| res.len() > 0)
+        let (bracket_open, bracket_close) = (&self.config.bracket_open, &self.config.bracket_close);
+        let is_terminator = |c: &char| self.terminators.contains(c);
+
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        // Closing chars of the quotes we are in
+        let mut closers: Vec<char> = Vec::new();
+        let mut in_markup = false;
+        let mut chars = article.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            let rest = &article[i..];
+
+            if self.config.syntax == Syntax::Bracket && rest.starts_with(bracket_open.as_str()) {
+                in_markup = true;
+            } else if in_markup && rest.starts_with(bracket_close.as_str()) {
+                in_markup = false;
+            } else if in_markup {
+                continue;
+            } else if c == '\n' {
+                // A line break always ends the sentence, even if a quote is left open
+                sentences.push(&article[start..i]);
+                start = i + c.len_utf8();
+                closers.clear();
+            } else if let Some((_, closer)) = QUOTES.iter().find(|q| q.0 == c) {
+                closers.push(*closer);
+            } else if closers.last() == Some(&c) {
+                closers.pop();
+            } else if closers.is_empty() && is_terminator(&c) {
+                // Keep the whole run of terminators, e.g. "！？" or "……"
+                let mut end = i + c.len_utf8();
+                while let Some((j, next)) = chars.next_if(|(_, next)| is_terminator(next)) {
+                    end = j + next.len_utf8();
+                }
+
+                sentences.push(&article[start..end]);
+                start = end;
+            }
+        }
+        sentences.push(&article[start..]);
+
+        sentences
+            .into_iter()
+            .map(|s| s.trim())
+            .filter(|res| !res.is_empty())
+    }
+
+    // Check the markups of the article, and report every problem found.
+    pub fn validate(&self, article: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let (bracket_open, bracket_close) = (&self.config.bracket_open, &self.config.bracket_close);
+        let open_len = bracket_open.chars().count();
+        let close_len = bracket_close.chars().count();
+        // The markups stop at the first char of the closing brackets
+        let close_first = bracket_close.chars().next().unwrap_or_default();
+
+        // (byte index, char offset) of the markup being read
+        let mut open: Option<(usize, usize)> = None;
+        let mut chars = article.char_indices().enumerate();
+
+        while let Some((offset, (i, _))) = chars.next() {
+            let rest = &article[i..];
+
+            if rest.starts_with(bracket_open.as_str()) {
+                match open {
+                    Some(_) => diagnostics.push(Diagnostic {
+                        offset,
+                        len: open_len,
+                        kind: DiagnosticKind::NestedMarkup,
+                    }),
+                    None => open = Some((i, offset)),
+                }
+                // Skip the rest of the brackets
+                chars.by_ref().take(open_len - 1).for_each(drop);
+            } else if rest.starts_with(bracket_close.as_str()) {
+                match open.take() {
+                    Some((start, start_offset)) => {
+                        let body = &article[start + bracket_open.len()..i];
+                        let mut fields: Vec<&str> =
+                            body.split(self.config.delimiter_field).collect();
+                        let mut start_offset = start_offset;
+
+                        // The word of a ruby markup comes right before the brackets
+                        if self.config.syntax == Syntax::Ruby {
+                            let word = self
+                                .regex_kanji
+                                .find(&article[..start])
+                                .map_or("", |m| m.as_str());
+                            start_offset -= word.chars().count();
+                            fields.insert(0, word);
+                        }
+
+                        let len = offset + close_len - start_offset;
+
+                        let mut kinds = self.validate_fields(&fields);
+                        if body.contains(close_first) {
+                            kinds.push(DiagnosticKind::BracketInMarkup(close_first));
+                        }
+
+                        diagnostics.extend(kinds.into_iter().map(|kind| Diagnostic {
+                            offset: start_offset,
+                            len,
+                            kind,
+                        }));
+                    }
+                    None => diagnostics.push(Diagnostic {
+                        offset,
+                        len: close_len,
+                        kind: DiagnosticKind::StrayClosingBracket,
+                    }),
+                }
+                chars.by_ref().take(close_len - 1).for_each(drop);
+            }
+        }
+
+        if let Some((_, offset)) = open {
+            diagnostics.push(Diagnostic {
+                offset,
+                len: open_len,
+                kind: DiagnosticKind::UnclosedBracket,
+            });
+        }
+
+        diagnostics
+    }
+
+    // [word, reading, annotation] -> problems of the fields
+    fn validate_fields(&self, fields: &[&str]) -> Vec<DiagnosticKind> {
+        let mut problems = Vec::new();
+
+        if fields[0].trim().is_empty() {
+            problems.push(DiagnosticKind::EmptyWord);
+        }
+
+        if fields.get(1).is_none_or(|f| f.trim().is_empty()) {
+            problems.push(DiagnosticKind::MissingReading);
+        }
+
+        let extra = fields
+            .iter()
+            .skip(3)
+            .filter(|f| !f.trim().is_empty())
+            .count();
+        if extra > 0 {
+            problems.push(DiagnosticKind::ExtraFields(extra));
+        }
+
+        problems
     }
 
-    // "word_1・hiragana_1・annotation_1"
+    // "word_1・hiragana_1・annotation_1" or "word_1(hiragana_1・annotation_1)"
     //  -> iter[word, hiragana, annotation]
     pub fn cap_fields_iter<'a>(&self, entry: &'a str) -> impl Iterator<Item = &'a str> {
-        entry
-            .split(self.delimiter_field)
-            .filter(|res| res.len() > 0)
+        let delimiter = self.config.delimiter_field;
+        let fields: Vec<&str> = match self.config.syntax {
+            Syntax::Bracket => entry.split(delimiter).collect(),
+            Syntax::Ruby => match entry.split_once(self.config.bracket_open.as_str()) {
+                Some((word, rest)) => {
+                    let rest = rest
+                        .strip_suffix(self.config.bracket_close.as_str())
+                        .unwrap_or(rest);
+                    std::iter::once(word).chain(rest.split(delimiter)).collect()
+                }
+                None => vec![entry],
+            },
+        };
+
+        fields.into_iter().filter(|res| !res.is_empty())
     }
 }
 
@@ -79,11 +300,42 @@ mod tests {
         let p = Parser::new();
         let mut iter = p.cap_sentences_iter(ARTICLE);
 
-        assert_eq!(iter.next(), Some("ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>まる<<中・なか>>、日本の<<自動車・じどうしゃ>>メーカーに<<影響・えいきょう・>>が<<広がっています・ひろがる・to spread out>>"));
-        assert_eq!(iter.next(), Some("トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました"));
+        assert_eq!(iter.next(), Some("ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>まる<<中・なか>>、日本の<<自動車・じどうしゃ>>メーカーに<<影響・えいきょう・>>が<<広がっています・ひろがる・to spread out>>。"));
+        assert_eq!(iter.next(), Some("トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。"));
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn cap_sentence_with_other_terminators() {
+        let p = Parser::new();
+        let sentences: Vec<&str> = p
+            .cap_sentences_iter("本当？うそ！？\n  そうですね……また明日!  Really?")
+            .collect();
+
+        assert_eq!(
+            sentences,
+            vec!["本当？", "うそ！？", "そうですね……", "また明日!", "Really?"]
+        );
+    }
+
+    #[test]
+    fn cap_sentence_keeps_quotes_and_markups_whole() {
+        let p = Parser::new();
+        let sentences: Vec<&str> = p
+            .cap_sentences_iter("「行くよ。早く！」と<<言った・いった・said? yes.>>。（注。）次\n「開いたまま\n最後")
+            .collect();
+
+        assert_eq!(
+            sentences,
+            vec![
+                "「行くよ。早く！」と<<言った・いった・said? yes.>>。",
+                "（注。）次",
+                "「開いたまま",
+                "最後"
+            ]
+        );
+    }
+
     #[test]
     fn clean_sentence() {
         let p = Parser::new();
@@ -91,6 +343,10 @@ mod tests {
             p.clean_sentence(SENTENCE),
             "トヨタ自動車はあすからロシアにある`工場`の`稼働`を。"
         );
+
+        // The original terminator is preserved
+        assert_eq!(p.clean_sentence("<<工場・こうじょう>>？"), "`工場`？");
+        assert_eq!(p.clean_sentence("「<<工場・こうじょう>>」"), "「`工場`」");
     }
 
     #[test]
@@ -106,6 +362,179 @@ mod tests {
         assert_eq!(iter.next(), None);
     }
 
+    #[test]
+    fn valid_article_has_no_diagnostics() {
+        let p = Parser::new();
+        assert!(p.validate(ARTICLE).is_empty());
+    }
+
+    #[test]
+    fn validate_entries() {
+        let p = Parser::new();
+
+        let d = p.validate("ある<<工場>>の<<・かどう>>を<<停止・ていし・stop・x>>。");
+        assert_eq!(
+            d,
+            vec![
+                Diagnostic {
+                    offset: 2,
+                    len: 6,
+                    kind: DiagnosticKind::MissingReading
+                },
+                Diagnostic {
+                    offset: 9,
+                    len: 8,
+                    kind: DiagnosticKind::EmptyWord
+                },
+                Diagnostic {
+                    offset: 18,
+                    len: 17,
+                    kind: DiagnosticKind::ExtraFields(1)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_brackets() {
+        let p = Parser::new();
+
+        let kinds = |article| {
+            p.validate(article)
+                .into_iter()
+                .map(|d| (d.offset, d.kind))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds("ある<<工場・こうじょう"),
+            vec![(2, DiagnosticKind::UnclosedBracket)]
+        );
+        assert_eq!(
+            kinds("ある工場>>の"),
+            vec![(4, DiagnosticKind::StrayClosingBracket)]
+        );
+        assert_eq!(
+            kinds("<<工<<場・ば>>・こう>>"),
+            vec![
+                (3, DiagnosticKind::NestedMarkup),
+                (13, DiagnosticKind::StrayClosingBracket)
+            ]
+        );
+
+        // The markup would be left out of the entries
+        let article = "ある<<工>場・こうじょう>>の";
+        assert_eq!(p.cap_entries_iter(article).count(), 0);
+        assert_eq!(
+            kinds(article),
+            vec![(2, DiagnosticKind::BracketInMarkup('>'))]
+        );
+        assert_eq!(
+            kinds("<<a>b・x>>"),
+            vec![(0, DiagnosticKind::BracketInMarkup('>'))]
+        );
+    }
+
+    #[test]
+    fn cap_sentence_of_other_languages() {
+        let p = Parser::with_config(ParserConfig::for_language(Lang::Korean)).unwrap();
+        let sentences: Vec<&str> = p
+            .cap_sentences_iter("<<공장・gongjang・factory.>>에 가요. 뭐?")
+            .collect();
+
+        assert_eq!(
+            sentences,
+            vec!["<<공장・gongjang・factory.>>에 가요.", "뭐?"]
+        );
+        assert_eq!(
+            p.clean_sentence("<<공장・gongjang>>에 가요"),
+            "`공장`에 가요."
+        );
+    }
+
+    #[test]
+    fn custom_brackets() {
+        let p = Parser::with_config(ParserConfig {
+            bracket_open: String::from("{"),
+            bracket_close: String::from("}"),
+            delimiter_field: '|',
+            ..ParserConfig::default()
+        })
+        .unwrap();
+        let s = "ロシアにある{工場|こうじょう}の{稼働|かどう|operation, running}を";
+
+        assert!(p.validate(s).is_empty());
+        assert_eq!(p.clean_sentence(s), "ロシアにある`工場`の`稼働`を。");
+
+        let entries: Vec<&str> = p.cap_entries_iter(s).collect();
+        assert_eq!(
+            entries,
+            vec!["工場|こうじょう", "稼働|かどう|operation, running"]
+        );
+
+        let fields: Vec<&str> = p.cap_fields_iter(entries[1]).collect();
+        assert_eq!(fields, vec!["稼働", "かどう", "operation, running"]);
+
+        let kinds: Vec<DiagnosticKind> = p
+            .validate("ある{工場}の}")
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::MissingReading,
+                DiagnosticKind::StrayClosingBracket
+            ]
+        );
+    }
+
+    #[test]
+    fn ruby_markups() {
+        let p = Parser::with_config(ParserConfig {
+            bracket_open: String::from("（"),
+            bracket_close: String::from("）"),
+            ..ParserConfig::ruby()
+        })
+        .unwrap();
+        assert_eq!(
+            p.clean_sentence("ロシアにある工場（こうじょう）の"),
+            "ロシアにある`工場`の。"
+        );
+
+        let p = Parser::with_config(ParserConfig::ruby()).unwrap();
+        let s = "ロシアにある工場(こうじょう)の稼働(かどう・operation, running)を";
+
+        assert!(p.validate(s).is_empty());
+        assert_eq!(p.clean_sentence(s), "ロシアにある`工場`の`稼働`を。");
+
+        let entries: Vec<&str> = p.cap_entries_iter(s).collect();
+        assert_eq!(
+            entries,
+            vec!["工場(こうじょう)", "稼働(かどう・operation, running)"]
+        );
+
+        let fields: Vec<&str> = p.cap_fields_iter(entries[1]).collect();
+        assert_eq!(fields, vec!["稼働", "かどう", "operation, running"]);
+
+        let d = p.validate("ロシアにある工場()の(かどう)");
+        assert_eq!(
+            d,
+            vec![
+                Diagnostic {
+                    offset: 6,
+                    len: 4,
+                    kind: DiagnosticKind::MissingReading
+                },
+                Diagnostic {
+                    offset: 11,
+                    len: 5,
+                    kind: DiagnosticKind::EmptyWord
+                },
+            ]
+        );
+    }
+
     #[test]
     fn cap_entry_field() {
         let p = Parser::new();
@@ -0,0 +1,203 @@
+use crate::{
+    error::{Error, Result},
+    language::Lang,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::ErrorKind};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    // <<word・reading・annotation>>
+    Bracket,
+    // 漢字(reading・annotation), the word being the kanji right before the brackets
+    Ruby,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ParserConfig {
+    pub language: Lang,
+    pub syntax: Syntax,
+    pub bracket_open: String,
+    pub bracket_close: String,
+    pub delimiter_field: char,
+    // None to use the ones of the language.
+    pub sentence_terminators: Option<Vec<char>>,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        ParserConfig {
+            language: Lang::default(),
+            syntax: Syntax::Bracket,
+            bracket_open: String::from("<<"),
+            bracket_close: String::from(">>"),
+            delimiter_field: '・',
+            sentence_terminators: None,
+        }
+    }
+}
+
+impl ParserConfig {
+    pub fn for_language(language: Lang) -> ParserConfig {
+        ParserConfig {
+            language,
+            ..ParserConfig::default()
+        }
+    }
+
+    pub fn terminators(&self) -> Vec<char> {
+        match &self.sentence_terminators {
+            Some(terminators) => terminators.to_owned(),
+            None => self.language.profile().sentence_terminators(),
+        }
+    }
+
+    // 漢字(かんじ・annotation)
+    pub fn ruby() -> ParserConfig {
+        ParserConfig {
+            syntax: Syntax::Ruby,
+            bracket_open: String::from("("),
+            bracket_close: String::from(")"),
+            ..ParserConfig::default()
+        }
+    }
+
+    // Load the config from a json file, missing settings fall back to the default ones.
+    pub fn load(path: &str) -> Result<ParserConfig> {
+        let content = fs::read_to_string(path).map_err(|err| Error::io(path, err))?;
+
+        serde_json::from_str(&content).map_err(|err| Error::Config {
+            path: path.to_owned(),
+            source: err,
+        })
+    }
+
+    // Same as load, but fall back to the default config if there is no file.
+    pub fn load_or_default(path: &str) -> Result<ParserConfig> {
+        match fs::metadata(path) {
+            Ok(_) => ParserConfig::load(path),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(ParserConfig::default()),
+            Err(err) => Err(Error::io(path, err)),
+        }
+    }
+
+    // Check the settings can make a parser.
+    pub fn check(&self) -> Result<()> {
+        let terminators = self.terminators();
+        let in_markups = |c: &char| {
+            *c == self.delimiter_field
+                || self.bracket_open.contains(*c)
+                || self.bracket_close.contains(*c)
+        };
+
+        let problem = if self.bracket_open.is_empty() || self.bracket_close.is_empty() {
+            Some("the brackets can't be empty")
+        } else if self.bracket_open == self.bracket_close {
+            Some("the opening and closing brackets must differ")
+        } else if self.bracket_open.contains(self.delimiter_field)
+            || self.bracket_close.contains(self.delimiter_field)
+        {
+            Some("the field delimiter can't be part of the brackets")
+        } else if terminators.is_empty() {
+            Some("at least one sentence terminator is needed")
+        } else if terminators.iter().any(in_markups) {
+            Some("the sentence terminators can't be part of the brackets or the field delimiter")
+        } else {
+            None
+        };
+
+        match problem {
+            Some(msg) => Err(Error::Syntax(msg.to_owned())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_load_partial_config() {
+        let path = ".test/parser_config.json";
+        fs::write(
+            path,
+            r#"{"bracket_open": "{", "bracket_close": "}", "delimiter_field": "|"}"#,
+        )
+        .unwrap();
+
+        let c = ParserConfig::load(path).unwrap();
+        assert_eq!(c.syntax, Syntax::Bracket);
+        assert_eq!(c.bracket_open, "{");
+        assert_eq!(c.delimiter_field, '|');
+        assert_eq!(c.language, Lang::Japanese);
+        assert_eq!(
+            c.terminators(),
+            Lang::Japanese.profile().sentence_terminators()
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn can_reject_invalid_config() {
+        let c = ParserConfig {
+            bracket_close: String::new(),
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        let c = ParserConfig {
+            delimiter_field: '<',
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        let c = ParserConfig {
+            sentence_terminators: Some(Vec::new()),
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        let c = ParserConfig {
+            bracket_open: String::from("|"),
+            bracket_close: String::from("|"),
+            delimiter_field: '/',
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        // A sentence would end inside the markups
+        let c = ParserConfig {
+            bracket_open: String::from("["),
+            bracket_close: String::from("]"),
+            delimiter_field: '!',
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        let c = ParserConfig {
+            bracket_close: String::from("?>"),
+            ..ParserConfig::default()
+        };
+        assert!(c.check().is_err());
+
+        assert!(ParserConfig::ruby().check().is_ok());
+    }
+
+    #[test]
+    fn falls_back_only_without_a_file() {
+        assert_eq!(
+            ParserConfig::load_or_default(".test/no_parser_config.json").unwrap(),
+            ParserConfig::default()
+        );
+
+        // The parent is a file, not a directory
+        assert!(matches!(
+            ParserConfig::load_or_default(".test/iamhere.txt/parser.json"),
+            Err(Error::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiagnosticKind {
+    MissingReading,
+    EmptyWord,
+    UnclosedBracket,
+    StrayClosingBracket,
+    NestedMarkup,
+    // A char starting the closing brackets, which would end the markup early.
+    BracketInMarkup(char),
+    // The number of fields found beyond word, reading and annotation.
+    ExtraFields(usize),
+}
+
+// A problem found in the markups, `offset` and `len` are counted in chars.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub offset: usize,
+    pub len: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::MissingReading => write!(f, "the word has no reading"),
+            DiagnosticKind::EmptyWord => write!(f, "the word is empty"),
+            DiagnosticKind::UnclosedBracket => write!(f, "the markup is never closed"),
+            DiagnosticKind::StrayClosingBracket => write!(f, "closing brackets without a markup"),
+            DiagnosticKind::NestedMarkup => write!(f, "markups can't be nested"),
+            DiagnosticKind::BracketInMarkup(c) => {
+                write!(
+                    f,
+                    "the markup can't contain '{}' of the closing brackets",
+                    c
+                )
+            }
+            DiagnosticKind::ExtraFields(n) => {
+                write!(f, "{} unknown field(s) after the annotation", n)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "at char {}, {}", self.offset, self.kind)
+    }
+}
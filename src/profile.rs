@@ -0,0 +1,263 @@
+use crate::{
+    error::{Error, Result},
+    library::{check_name, load_current, path_str, save_current, Library, DEFAULT_BOOK},
+    session::QuizMode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Used by scripts when nobody has been asked for a name.
+pub const DEFAULT_USER: &str = "default";
+
+const USERS_DIR: &str = "users";
+const STATE_FILE: &str = "users.json";
+const PROFILE_FILE: &str = "profile.json";
+// Where the book was saved before the library, taken over by the first user.
+const LEGACY_BOOK: &str = ".prod/book.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    // Words to review a day (UTC), 0 for all the due words.
+    pub daily_goal: usize,
+    // The quiz modes taking turns in the sessions.
+    pub quiz_modes: Vec<QuizMode>,
+    // Show the other target words of the sentence in a cloze, or hide them too.
+    pub cloze_show_others: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            daily_goal: 20,
+            quiz_modes: vec![QuizMode::Reading],
+            cloze_show_others: true,
+        }
+    }
+}
+
+// A user with their own library of books and settings.
+pub struct Profile {
+    pub name: String,
+    pub settings: Settings,
+    dir: PathBuf,
+}
+
+impl Profile {
+    pub fn library(&self) -> Result<Library> {
+        Library::open(self.dir.clone())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = path_str(&self.dir.join(PROFILE_FILE));
+        let json = serde_json::to_string_pretty(&self.settings).map_err(Error::Serialize)?;
+
+        fs::write(&path, json).map_err(|err| Error::io(&path, err))
+    }
+}
+
+// The user profiles sharing a data directory.
+pub struct Users {
+    root: PathBuf,
+}
+
+impl Users {
+    pub fn open(root: impl Into<PathBuf>) -> Result<Users> {
+        let root = root.into();
+        let users = root.join(USERS_DIR);
+        fs::create_dir_all(&users).map_err(|err| Error::io(&path_str(&users), err))?;
+
+        Ok(Users { root })
+    }
+
+    fn dir(&self, name: &str) -> PathBuf {
+        self.root.join(USERS_DIR).join(name)
+    }
+
+    fn state_path(&self) -> String {
+        path_str(&self.root.join(STATE_FILE))
+    }
+
+    pub fn list(&self) -> Result<Vec<String>> {
+        let dir = self.root.join(USERS_DIR);
+        let entries = fs::read_dir(&dir).map_err(|err| Error::io(&path_str(&dir), err))?;
+
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.join(PROFILE_FILE).is_file())
+            .filter_map(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    // A name which isn't a valid one, e.g. "../x", is never there.
+    pub fn contains(&self, name: &str) -> bool {
+        check_name(name).is_ok() && self.dir(name).join(PROFILE_FILE).is_file()
+    }
+
+    fn check_exists(&self, name: &str) -> Result<()> {
+        check_name(name)?;
+        match self.contains(name) {
+            true => Ok(()),
+            false => Err(Error::UnknownUser(name.to_owned())),
+        }
+    }
+
+    // The user switched to last, or the only one.
+    pub fn current(&self) -> Result<Option<String>> {
+        let current = load_current(&self.state_path())?;
+        if let Some(name) = current.filter(|name| self.contains(name)) {
+            return Ok(Some(name));
+        }
+
+        let mut names = self.list()?;
+        match names.len() {
+            1 => Ok(names.pop()),
+            _ => Ok(None),
+        }
+    }
+
+    // Create a user with the default settings and switch to it.
+    // The first user takes over the books saved before there were users,
+    //  and the one saved before there was a library.
+    pub fn create(&self, name: &str) -> Result<Profile> {
+        check_name(name)?;
+        if self.contains(name) {
+            return Err(Error::UserExists(name.to_owned()));
+        }
+        let first = self.list()?.is_empty();
+
+        let dir = self.dir(name);
+        fs::create_dir_all(&dir).map_err(|err| Error::io(&path_str(&dir), err))?;
+        let profile = Profile {
+            name: name.to_owned(),
+            settings: Settings::default(),
+            dir,
+        };
+        profile.save()?;
+
+        if first {
+            let library = profile.library()?;
+            library.adopt(&self.root)?;
+
+            if library.adopt_book(Path::new(LEGACY_BOOK))? {
+                eprintln!(
+                    "Your book {} is now the book {} of the library in {}.",
+                    LEGACY_BOOK,
+                    DEFAULT_BOOK,
+                    library.root().display()
+                );
+            }
+        }
+        self.switch(name)?;
+
+        Ok(profile)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Profile> {
+        self.check_exists(name)?;
+
+        let dir = self.dir(name);
+        let path = path_str(&dir.join(PROFILE_FILE));
+        let content = fs::read_to_string(&path).map_err(|err| Error::io(&path, err))?;
+        let settings = serde_json::from_str(&content).map_err(|err| Error::parse(&path, err))?;
+
+        Ok(Profile {
+            name: name.to_owned(),
+            settings,
+            dir,
+        })
+    }
+
+    pub fn switch(&self, name: &str) -> Result<()> {
+        self.check_exists(name)?;
+
+        save_current(&self.state_path(), Some(name))
+    }
+
+    // Delete the user with all their books.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        self.check_exists(name)?;
+
+        let dir = self.dir(name);
+        fs::remove_dir_all(&dir).map_err(|err| Error::io(&path_str(&dir), err))?;
+
+        if load_current(&self.state_path())?.as_deref() == Some(name) {
+            save_current(&self.state_path(), None)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::language::Lang;
+
+    fn open_empty(name: &str) -> Users {
+        let root = format!(".test/users_{}", name);
+        let _ = fs::remove_dir_all(&root);
+
+        Users::open(root).unwrap()
+    }
+
+    #[test]
+    fn users_own_their_books_and_settings() {
+        let users = open_empty("own");
+        assert_eq!(users.current().unwrap(), None);
+
+        let mut yan = users.create("Yan").unwrap();
+        yan.settings.daily_goal = 5;
+        yan.save().unwrap();
+        yan.library().unwrap().create("jp", Lang::Japanese).unwrap();
+
+        let kim = users.create("Kim").unwrap();
+        assert!(kim.library().unwrap().list().unwrap().is_empty());
+        assert_eq!(users.current().unwrap().as_deref(), Some("Kim"));
+
+        let yan = users.load("Yan").unwrap();
+        assert_eq!(yan.settings.daily_goal, 5);
+        assert_eq!(yan.library().unwrap().list().unwrap(), vec!["jp"]);
+        assert_eq!(users.list().unwrap(), vec!["Kim", "Yan"]);
+
+        users.delete("Kim").unwrap();
+        assert_eq!(users.current().unwrap().as_deref(), Some("Yan"));
+        assert!(matches!(users.load("Kim"), Err(Error::UnknownUser(_))));
+        assert!(matches!(users.create("Yan"), Err(Error::UserExists(_))));
+        // Names can't reach out of the data directory
+        assert!(!users.contains("../users_own/users/Yan"));
+        assert!(matches!(
+            users.load("../users_own/users/Yan"),
+            Err(Error::InvalidName(_))
+        ));
+
+        fs::remove_dir_all(users.root()).unwrap();
+    }
+
+    #[test]
+    fn first_user_takes_over_the_books() {
+        let users = open_empty("adopt");
+        Library::open(users.root())
+            .unwrap()
+            .create("jp", Lang::Japanese)
+            .unwrap();
+
+        let yan = users.create("Yan").unwrap();
+        let library = yan.library().unwrap();
+        assert_eq!(library.list().unwrap(), vec!["jp"]);
+        assert_eq!(library.current().unwrap(), "jp");
+
+        fs::remove_dir_all(users.root()).unwrap();
+    }
+}
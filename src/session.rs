@@ -0,0 +1,445 @@
+use crate::{
+    error::Result,
+    history::{self, Review},
+    profile::Settings,
+    study_book::{
+        schedule::{self, Grade},
+        word::WordEntry,
+        StudyBook,
+    },
+    ui,
+};
+use console::style;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr, time::Instant};
+
+pub mod choice;
+pub mod cloze;
+pub mod meaning;
+
+// Answer with this to end the session early.
+pub const QUIT: &str = ":q";
+
+// The kinds of questions asked in a session.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuizMode {
+    // Type the reading of the word shown in its sentence, in kana or romaji for Japanese.
+    Reading,
+    // Type the word blanked out of its sentence.
+    Cloze,
+    // Type the meaning of the word, any of its glosses.
+    Meaning,
+    // Pick the reading or meaning of the word among others from the book.
+    Choice,
+}
+
+impl QuizMode {
+    pub fn all() -> Vec<QuizMode> {
+        vec![
+            QuizMode::Reading,
+            QuizMode::Cloze,
+            QuizMode::Meaning,
+            QuizMode::Choice,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuizMode::Reading => "reading",
+            QuizMode::Cloze => "cloze",
+            QuizMode::Meaning => "meaning",
+            QuizMode::Choice => "choice",
+        }
+    }
+
+    // Whether the word has what the question needs: a sentence or a meaning.
+    pub fn can_quiz(&self, entry: &WordEntry) -> bool {
+        match self {
+            QuizMode::Reading | QuizMode::Choice => true,
+            QuizMode::Cloze => entry.context().is_some(),
+            QuizMode::Meaning => entry
+                .annotation
+                .as_deref()
+                .is_some_and(|a| !meaning::glosses(a).is_empty()),
+        }
+    }
+}
+
+impl fmt::Display for QuizMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for QuizMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        QuizMode::all()
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = QuizMode::all().iter().map(|m| m.name()).collect();
+                format!("unknown quiz mode {}, try {}", s, names.join(", "))
+            })
+    }
+}
+
+// A graded answer, as typed or picked, in the mode the question was asked.
+struct Answered {
+    mode: QuizMode,
+    grade: Grade,
+    answer: String,
+}
+
+impl Answered {
+    fn new(mode: QuizMode, grade: Grade, answer: &str) -> Answered {
+        Answered {
+            mode,
+            grade,
+            answer: answer.trim().to_owned(),
+        }
+    }
+}
+
+// What came of a question.
+enum Outcome {
+    Answered(Answered),
+    // The word can't be asked, e.g. its sentence is missing from the book
+    Skipped,
+    Quit,
+}
+
+pub struct Summary {
+    pub reviewed: usize,
+    pub correct: usize,
+}
+
+// The preferred modes take turns. A word which can't be quizzed in the mode of its turn,
+//  e.g. the cloze of a word without sentence, goes to the next preferred mode it can be,
+//  else to the reading one.
+fn pick_mode(settings: &Settings, turn: usize, can_quiz: impl Fn(QuizMode) -> bool) -> QuizMode {
+    let modes = &settings.quiz_modes;
+
+    (0..modes.len())
+        .map(|i| modes[(turn + i) % modes.len()])
+        .find(|mode| can_quiz(*mode))
+        .unwrap_or(QuizMode::Reading)
+}
+
+// Show the word in its sentence and ask for its reading.
+fn ask_reading(book: &StudyBook, id: &str) -> Result<Outcome> {
+    let entry = match book.word(id) {
+        Some(entry) => entry,
+        None => return Ok(Outcome::Skipped),
+    };
+    let word = entry.word.word();
+
+    if let Some(s) = entry.context().and_then(|s_id| book.sentence(s_id)) {
+        println!(
+            "{}",
+            s.sentence.cloze(
+                word,
+                |w| style(w).bold().underlined().to_string(),
+                str::to_owned
+            )
+        );
+    }
+
+    let language = book.language.profile();
+    let hint = match language.input_hint() {
+        Some(hint) => format!(" ({})", hint),
+        None => String::new(),
+    };
+    let answer = ui::request_answer(&format!(
+        "What is the {} of {}?{}",
+        language.reading_label(),
+        style(word).bold(),
+        hint
+    ))?;
+    if answer.trim() == QUIT {
+        return Ok(Outcome::Quit);
+    }
+
+    if entry.check_reading(&answer, language) {
+        return Ok(Outcome::Answered(Answered::new(
+            QuizMode::Reading,
+            Grade::Good,
+            &answer,
+        )));
+    }
+
+    // Point at the detail which was missed
+    if let Some(miss) = language.near_miss(&answer, &entry.hiragana) {
+        println!("So close, {}.", miss);
+    }
+
+    Ok(Outcome::Answered(Answered::new(
+        QuizMode::Reading,
+        Grade::Again,
+        &answer,
+    )))
+}
+
+// Show the sentence with the word blanked out and ask for the word.
+fn ask_cloze(book: &StudyBook, id: &str, settings: &Settings) -> Result<Outcome> {
+    let entry = match book.word(id) {
+        Some(entry) => entry,
+        None => return Ok(Outcome::Skipped),
+    };
+    let sentence = match entry.context().and_then(|s_id| book.sentence(s_id)) {
+        Some(s) => &s.sentence,
+        None => return Ok(Outcome::Skipped),
+    };
+
+    let blank = |w: &str| style("＿".repeat(w.chars().count())).bold().to_string();
+    println!(
+        "{}",
+        cloze::render(
+            sentence,
+            entry.word.word(),
+            settings.cloze_show_others,
+            blank
+        )
+    );
+
+    let answer = ui::request_answer("Which word is missing?")?;
+    if answer.trim() == QUIT {
+        return Ok(Outcome::Quit);
+    }
+
+    let verdict = cloze::check(entry, &answer, book.language.profile());
+    if verdict == cloze::ClozeVerdict::ReadingMatch {
+        println!("Right reading, now try to write it as in the sentence.");
+    }
+
+    Ok(Outcome::Answered(Answered::new(
+        QuizMode::Cloze,
+        verdict.grade(),
+        &answer,
+    )))
+}
+
+// Show the word and ask for its meaning, the user has the last word on a wrong answer.
+fn ask_meaning(book: &StudyBook, id: &str) -> Result<Outcome> {
+    let entry = match book.word(id) {
+        Some(entry) => entry,
+        None => return Ok(Outcome::Skipped),
+    };
+    let annotation = match &entry.annotation {
+        Some(a) => a,
+        None => return Ok(Outcome::Skipped),
+    };
+
+    let answer = ui::request_answer(&format!(
+        "What does {} mean?",
+        style(entry.word.word()).bold()
+    ))?;
+    if answer.trim() == QUIT {
+        return Ok(Outcome::Quit);
+    }
+
+    let verdict = meaning::check(annotation, &answer);
+    match &verdict {
+        meaning::MeaningVerdict::Close(gloss) => println!("Close enough, it's spelled {}.", gloss),
+        meaning::MeaningVerdict::Wrong if !answer.trim().is_empty() => {
+            println!("Expected {}.", annotation);
+            // A synonym the annotation doesn't list
+            if ui::confirm("Count your answer as right?")? {
+                return Ok(Outcome::Answered(Answered::new(
+                    QuizMode::Meaning,
+                    Grade::Good,
+                    &answer,
+                )));
+            }
+        }
+        _ => (),
+    }
+
+    Ok(Outcome::Answered(Answered::new(
+        QuizMode::Meaning,
+        verdict.grade(),
+        &answer,
+    )))
+}
+
+// Show the word and let the user pick its reading or meaning.
+// Asks for the reading instead when the book has no other word to choose from.
+fn ask_choice(book: &StudyBook, id: &str, seed: u64) -> Result<Outcome> {
+    let entry = match book.word(id) {
+        Some(entry) => entry,
+        None => return Ok(Outcome::Skipped),
+    };
+    let ask = choice::Ask::of(entry);
+    let question = match choice::question(book, entry, ask, seed) {
+        Some(question) => question,
+        None => return ask_reading(book, id),
+    };
+
+    let prompt = match ask {
+        choice::Ask::Reading => format!(
+            "What is the {} of {}?",
+            book.language.profile().reading_label(),
+            style(entry.word.word()).bold()
+        ),
+        choice::Ask::Meaning => format!("What does {} mean?", style(entry.word.word()).bold()),
+    };
+
+    // Escape quits
+    let picked = match ui::choose_answer(&prompt, &question.choices)? {
+        Some(picked) => picked,
+        None => return Ok(Outcome::Quit),
+    };
+    let grade = match picked == question.answer {
+        true => Grade::Good,
+        false => Grade::Again,
+    };
+
+    Ok(Outcome::Answered(Answered::new(
+        QuizMode::Choice,
+        grade,
+        &question.choices[picked],
+    )))
+}
+
+// Walk the words due today, up to the daily goal, and quiz each word in the preferred modes.
+// The goal counts the reviews of the earlier sessions of the day too.
+// The book is saved to `path` after every review so no progress is lost.
+pub fn study(book: &mut StudyBook, path: &str, settings: &Settings) -> Result<Summary> {
+    let mut summary = Summary {
+        reviewed: 0,
+        correct: 0,
+    };
+    let reviewed_before = history::count_on_day(path, schedule::now())?;
+
+    for (turn, id) in book.due_today(schedule::now()).into_iter().enumerate() {
+        let reviewed_today = reviewed_before + summary.reviewed;
+        if settings.daily_goal > 0 && reviewed_today >= settings.daily_goal {
+            println!();
+            println!(
+                "{} Daily goal of {} words reached.",
+                style("★").yellow(),
+                settings.daily_goal
+            );
+            break;
+        }
+
+        let mode = match book.word(&id) {
+            Some(entry) => pick_mode(settings, turn, |mode| mode.can_quiz(entry)),
+            None => continue,
+        };
+
+        println!();
+        let started = Instant::now();
+        let outcome = match mode {
+            QuizMode::Reading => ask_reading(book, &id)?,
+            QuizMode::Cloze => ask_cloze(book, &id, settings)?,
+            QuizMode::Meaning => ask_meaning(book, &id)?,
+            QuizMode::Choice => ask_choice(book, &id, schedule::now())?,
+        };
+
+        let Answered {
+            mode,
+            grade,
+            answer,
+        } = match outcome {
+            Outcome::Answered(answered) => answered,
+            Outcome::Skipped => continue,
+            Outcome::Quit => break,
+        };
+        let response_ms = started.elapsed().as_millis() as u64;
+
+        // Grade it and show the correct answer
+        let now = schedule::now();
+        if book.grade_word(&id, grade, now).is_none() {
+            continue;
+        }
+
+        summary.reviewed += 1;
+
+        if let Some(entry) = book.word(&id) {
+            let meaning = match &entry.annotation {
+                Some(a) if !a.is_empty() => format!(" ({})", a),
+                _ => String::new(),
+            };
+            let answer = format!("{} {}{}", entry.word.word(), entry.hiragana, meaning);
+
+            match grade {
+                Grade::Again => println!("{} {}", style("✘").red(), answer),
+                Grade::Hard => println!("{} {}", style("~").yellow(), answer),
+                Grade::Good | Grade::Easy => {
+                    summary.correct += 1;
+                    println!("{} {}", style("✔").green(), answer);
+                }
+            }
+        }
+
+        book.save_json(path)?;
+        history::append(
+            path,
+            &Review {
+                word_id: id,
+                timestamp: now,
+                mode,
+                answer,
+                correct: matches!(grade, Grade::Good | Grade::Easy),
+                response_ms,
+            },
+        )?;
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::study_book::word::Word;
+
+    #[test]
+    fn modes_take_turns() {
+        let settings = Settings {
+            quiz_modes: vec![QuizMode::Cloze, QuizMode::Reading],
+            ..Settings::default()
+        };
+
+        let any = |_| true;
+
+        assert_eq!(pick_mode(&settings, 0, any), QuizMode::Cloze);
+        assert_eq!(pick_mode(&settings, 1, any), QuizMode::Reading);
+        assert_eq!(pick_mode(&settings, 2, any), QuizMode::Cloze);
+        // A word without sentence can't be a cloze
+        assert_eq!(
+            pick_mode(&settings, 0, |mode| mode != QuizMode::Cloze),
+            QuizMode::Reading
+        );
+
+        let settings = Settings {
+            quiz_modes: vec![],
+            ..Settings::default()
+        };
+        assert_eq!(pick_mode(&settings, 3, any), QuizMode::Reading);
+    }
+
+    #[test]
+    fn words_without_meaning_go_to_another_mode() {
+        let book =
+            StudyBook::from_article("<<工場・こうじょう・factory>>の<<稼働・かどう>>").unwrap();
+        let annotated = book.word(&Word::from("工場").id()).unwrap();
+        let plain = book.word(&Word::from("稼働").id()).unwrap();
+        let settings = Settings {
+            quiz_modes: vec![QuizMode::Meaning, QuizMode::Cloze],
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            pick_mode(&settings, 0, |mode| mode.can_quiz(annotated)),
+            QuizMode::Meaning
+        );
+        assert_eq!(
+            pick_mode(&settings, 0, |mode| mode.can_quiz(plain)),
+            QuizMode::Cloze
+        );
+    }
+}
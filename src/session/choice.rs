@@ -0,0 +1,147 @@
+use crate::{
+    id_source::fnv1a,
+    study_book::{word::WordEntry, StudyBook},
+};
+
+// The wrong choices offered along with the right one.
+pub const DISTRACTORS: usize = 3;
+
+// What a multiple choice question asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ask {
+    Reading,
+    Meaning,
+}
+
+impl Ask {
+    // The meaning and the reading of an annotated word take turns.
+    pub fn of(entry: &WordEntry) -> Ask {
+        match &entry.annotation {
+            Some(a) if !a.trim().is_empty() && entry.schedule.reviews % 2 == 1 => Ask::Meaning,
+            _ => Ask::Reading,
+        }
+    }
+
+    fn value<'a>(&self, entry: &'a WordEntry) -> Option<&'a str> {
+        let value = match self {
+            Ask::Reading => Some(entry.hiragana.as_str()),
+            Ask::Meaning => entry.annotation.as_deref(),
+        };
+        value.map(str::trim).filter(|v| !v.is_empty())
+    }
+}
+
+pub struct Question {
+    pub choices: Vec<String>,
+    pub answer: usize,
+}
+
+// A stable shuffle: the same seed gives the same order, whatever the build.
+fn shuffle_key(seed: u64, value: &str) -> u64 {
+    let bytes: Vec<u8> = seed
+        .to_le_bytes()
+        .into_iter()
+        .chain(value.bytes())
+        .collect();
+    fnv1a(&bytes)
+}
+
+// How alike two words look: the chars they share, and more if they are as long.
+fn likeness(a: &str, b: &str) -> usize {
+    let shared = a.chars().filter(|c| b.contains(*c)).count();
+    let same_length = usize::from(a.chars().count() == b.chars().count());
+
+    shared + 2 * same_length
+}
+
+// Pick the wrong choices among the other words of the book, the ones with
+//  a reading alike or a word as long first, so the answer doesn't stand out.
+pub fn distractors(book: &StudyBook, entry: &WordEntry, ask: Ask, seed: u64) -> Vec<String> {
+    let answer = match ask.value(entry) {
+        Some(answer) => answer,
+        None => return vec![],
+    };
+
+    let mut candidates: Vec<(usize, u64, &str)> = book
+        .words_iter()
+        .filter_map(|(_, other)| {
+            let value = ask.value(other)?;
+            let likeness = likeness(&other.hiragana, &entry.hiragana)
+                + likeness(other.word.word(), entry.word.word());
+            Some((likeness, shuffle_key(seed, value), value))
+        })
+        .filter(|(_, _, value)| *value != answer)
+        .collect();
+
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    let mut distractors: Vec<String> = vec![];
+    for (_, _, value) in candidates {
+        if !distractors.iter().any(|d| d == value) {
+            distractors.push(value.to_owned());
+        }
+        if distractors.len() == DISTRACTORS {
+            break;
+        }
+    }
+
+    distractors
+}
+
+// The question on the word, None when the book has no other word to mix in.
+pub fn question(book: &StudyBook, entry: &WordEntry, ask: Ask, seed: u64) -> Option<Question> {
+    let answer = ask.value(entry)?.to_owned();
+    let mut choices = distractors(book, entry, ask, seed);
+    if choices.is_empty() {
+        return None;
+    }
+
+    choices.push(answer.clone());
+    choices.sort_by_key(|c| shuffle_key(seed, c));
+    let answer = choices.iter().position(|c| *c == answer)?;
+
+    Some(Question { choices, answer })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::study_book::word::Word;
+
+    const ARTICLE: &str = "<<工場・こうじょう・factory>>と<<工事・こうじ・construction>>と\
+        <<稼働・かどう・operation>>と<<停止・ていし>>と<<機械・きかい>>と<<東京都・とうきょうと>>";
+
+    #[test]
+    fn prefers_alike_distractors() {
+        let book = StudyBook::from_article(ARTICLE).unwrap();
+        let entry = book.word(&Word::from("工場").id()).unwrap();
+
+        let readings = distractors(&book, entry, Ask::Reading, 7);
+        assert_eq!(readings.len(), DISTRACTORS);
+        assert_eq!(readings[0], "こうじ");
+        assert!(!readings.contains(&String::from("こうじょう")));
+
+        // Only the annotated words have a meaning to offer
+        let meanings = distractors(&book, entry, Ask::Meaning, 7);
+        assert_eq!(meanings, vec!["construction", "operation"]);
+    }
+
+    #[test]
+    fn mixes_the_answer_in() {
+        let book = StudyBook::from_article(ARTICLE).unwrap();
+        let entry = book.word(&Word::from("工場").id()).unwrap();
+
+        let q = question(&book, entry, Ask::Reading, 7).unwrap();
+        assert_eq!(q.choices.len(), DISTRACTORS + 1);
+        assert_eq!(q.choices[q.answer], "こうじょう");
+        // Stable for the same seed
+        assert_eq!(
+            question(&book, entry, Ask::Reading, 7).unwrap().choices,
+            q.choices
+        );
+
+        let lonely = StudyBook::from_article("<<工場・こうじょう>>").unwrap();
+        let entry = lonely.word(&Word::from("工場").id()).unwrap();
+        assert!(question(&lonely, entry, Ask::Reading, 7).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use crate::{
+    language::Language,
+    study_book::{schedule::Grade, sentence::Sentence, word::WordEntry},
+};
+
+// Shown in place of the other target words when they are hidden.
+const HIDDEN: &str = "…";
+
+// How a cloze answer compares to the blanked word.
+#[derive(Debug, PartialEq)]
+pub enum ClozeVerdict {
+    // The word itself
+    Exact,
+    // Its reading, right but not written as in the sentence
+    ReadingMatch,
+    Wrong,
+}
+
+impl ClozeVerdict {
+    pub fn grade(&self) -> Grade {
+        match self {
+            ClozeVerdict::Exact => Grade::Good,
+            ClozeVerdict::ReadingMatch => Grade::Hard,
+            ClozeVerdict::Wrong => Grade::Again,
+        }
+    }
+}
+
+// The sentence with `word` blanked out by `blank`.
+// The other target words are shown as they are, or hidden too.
+pub fn render(
+    sentence: &Sentence,
+    word: &str,
+    show_others: bool,
+    blank: impl Fn(&str) -> String,
+) -> String {
+    sentence.cloze(word, blank, |other| match show_others {
+        true => other.to_owned(),
+        false => String::from(HIDDEN),
+    })
+}
+
+pub fn check(entry: &WordEntry, answer: &str, language: &dyn Language) -> ClozeVerdict {
+    let normalized = language.normalize(answer);
+
+    if normalized.is_empty() {
+        ClozeVerdict::Wrong
+    } else if normalized == language.normalize(entry.word.word()) {
+        ClozeVerdict::Exact
+    } else if entry.check_reading(answer, language) {
+        ClozeVerdict::ReadingMatch
+    } else {
+        ClozeVerdict::Wrong
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        language::Lang,
+        study_book::{word::Word, StudyBook},
+    };
+
+    const ARTICLE: &str = "ロシアにある<<工場・こうじょう>>の<<稼働・かどう>>を停止。";
+
+    #[test]
+    fn blanks_the_current_word() {
+        let s = Sentence::from("ロシアにある`工場`の`稼働`を停止。");
+        let blank = |w: &str| "＿".repeat(w.chars().count());
+
+        assert_eq!(
+            render(&s, "工場", true, blank),
+            "ロシアにある＿＿の稼働を停止。"
+        );
+        assert_eq!(
+            render(&s, "稼働", false, blank),
+            "ロシアにある…の＿＿を停止。"
+        );
+    }
+
+    #[test]
+    fn grades_exact_reading_and_wrong_answers() {
+        let book = StudyBook::from_article(ARTICLE).unwrap();
+        let entry = book.word(&Word::from("工場").id()).unwrap();
+        let ja = Lang::Japanese.profile();
+
+        assert_eq!(check(entry, " 工場", ja), ClozeVerdict::Exact);
+        assert_eq!(check(entry, "コウジョウ", ja), ClozeVerdict::ReadingMatch);
+        assert_eq!(check(entry, "稼働", ja), ClozeVerdict::Wrong);
+        assert_eq!(check(entry, "", ja), ClozeVerdict::Wrong);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::study_book::schedule::Grade;
+
+// Words which don't change the meaning of a gloss: "to run", "a factory".
+const FILLERS: [&str; 4] = ["to", "a", "an", "the"];
+
+// How a meaning answer compares to the glosses of the word.
+#[derive(Debug, PartialEq)]
+pub enum MeaningVerdict {
+    // One of the glosses
+    Exact,
+    // One of the glosses, give or take a typo
+    Close(String),
+    Wrong,
+}
+
+impl MeaningVerdict {
+    pub fn grade(&self) -> Grade {
+        match self {
+            MeaningVerdict::Exact | MeaningVerdict::Close(_) => Grade::Good,
+            MeaningVerdict::Wrong => Grade::Again,
+        }
+    }
+}
+
+// "Operation of a machine, running" -> ["operation of a machine", "running"]
+// The remarks are dropped first, as they may list things too: "work (of a machine, engine)"
+pub fn glosses(annotation: &str) -> Vec<String> {
+    strip_remarks(annotation)
+        .split([',', ';'])
+        .map(simplify)
+        .filter(|g| !g.is_empty())
+        .collect()
+}
+
+// The text without the remarks in parentheses.
+fn strip_remarks(text: &str) -> String {
+    let mut plain = String::new();
+    let mut depth = 0;
+
+    for c in text.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = std::cmp::max(depth, 1) - 1,
+            _ if depth == 0 => plain.push(c),
+            _ => (),
+        }
+    }
+
+    plain
+}
+
+// Lowercase, without the remarks in parentheses nor the leading fillers.
+fn simplify(text: &str) -> String {
+    let plain = strip_remarks(text).to_lowercase();
+
+    let mut words: Vec<&str> = plain.split_whitespace().collect();
+    while words.len() > 1 && FILLERS.contains(&words[0]) {
+        words.remove(0);
+    }
+
+    words.join(" ")
+}
+
+// The number of edits turning `a` into `b`.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+// Short glosses must be spelled right, long ones can take up to two typos.
+fn tolerance(gloss: &str) -> usize {
+    match gloss.chars().count() {
+        0..=4 => 0,
+        5..=9 => 1,
+        _ => 2,
+    }
+}
+
+pub fn check(annotation: &str, answer: &str) -> MeaningVerdict {
+    let answer = simplify(answer);
+    if answer.is_empty() {
+        return MeaningVerdict::Wrong;
+    }
+
+    let glosses = glosses(annotation);
+    if glosses.contains(&answer) {
+        return MeaningVerdict::Exact;
+    }
+
+    glosses
+        .into_iter()
+        .filter(|g| distance(&answer, g) <= tolerance(g))
+        .min_by_key(|g| distance(&answer, g))
+        .map_or(MeaningVerdict::Wrong, MeaningVerdict::Close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_glosses() {
+        assert_eq!(
+            glosses("Operation of a machine, running; to work (of a machine)"),
+            vec!["operation of a machine", "running", "work"]
+        );
+        assert_eq!(
+            glosses("to work (of a machine, engine), to run"),
+            vec!["work", "run"]
+        );
+        assert_eq!(glosses(" , "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn tolerates_typos() {
+        let annotation = "operation of a machine, running, to work";
+
+        assert_eq!(check(annotation, "Running "), MeaningVerdict::Exact);
+        assert_eq!(check(annotation, "to work"), MeaningVerdict::Exact);
+        assert_eq!(
+            check(annotation, "runing"),
+            MeaningVerdict::Close(String::from("running"))
+        );
+        assert_eq!(
+            check(annotation, "operatoin of a machine"),
+            MeaningVerdict::Close(String::from("operation of a machine"))
+        );
+        // Too short for a typo
+        assert_eq!(check(annotation, "wark"), MeaningVerdict::Wrong);
+        assert_eq!(check(annotation, "factory"), MeaningVerdict::Wrong);
+        assert_eq!(check(annotation, ""), MeaningVerdict::Wrong);
+    }
+}
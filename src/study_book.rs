@@ -1,34 +1,88 @@
-use crate::parser::Parser;
+use crate::{
+    backup,
+    error::{Error, Result},
+    language::Lang,
+    parser::Parser,
+};
+use schedule::{end_of_day, Grade, Schedule};
 use sentence::{Sentence, SentenceEntry, SentenceEntryMap};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use status::Status;
-use std::{collections::HashMap, fs, hash::Hash};
+use std::collections::HashMap;
 use word::{Word, WordEntry, WordEntryMap};
 
+pub mod anki;
+pub mod merge;
+pub mod migrate;
+pub mod schedule;
 pub mod sentence;
 pub mod status;
+pub mod table;
 pub mod word;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Default)]
 pub struct StudyObjectCollection<T> {
     pub achived: Option<T>,
     pub backlog: Option<T>,
 }
 
+// A word reaching this level is considered mastered and gets archived.
+pub const MASTERY_LEVEL: u8 = 5;
+
+// Remove an entry from an optional map, leaving None behind once it's empty.
+fn take_entry<V>(map: &mut Option<HashMap<String, V>>, id: &str) -> Option<V> {
+    let m = map.as_mut()?;
+    let v = m.remove(id);
+
+    if m.is_empty() {
+        *map = None;
+    }
+
+    v
+}
+
+fn put_entry<V>(map: &mut Option<HashMap<String, V>>, id: String, v: V) {
+    map.get_or_insert_with(HashMap::new).insert(id, v);
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct StudyBook {
+    // The layout of the saved book, older ones are upgraded on load, see migrate.
+    pub format_version: u64,
+    pub language: Lang,
     pub words: StudyObjectCollection<WordEntryMap>,
     pub sentences: StudyObjectCollection<SentenceEntryMap>,
 }
 
+impl Default for StudyBook {
+    fn default() -> Self {
+        StudyBook {
+            format_version: migrate::FORMAT_VERSION,
+            language: Lang::default(),
+            words: StudyObjectCollection::default(),
+            sentences: StudyObjectCollection::default(),
+        }
+    }
+}
+
 impl StudyBook {
-    pub fn from_article(article: &str) -> StudyBook {
+    // Build a book from the article marked up in the default syntax.
+    pub fn from_article(article: &str) -> Result<StudyBook> {
+        StudyBook::from_article_with(article, &Parser::new())
+    }
+
+    // Build a book from the marked-up article.
+    // Fail with the diagnostics if any markup is invalid.
+    pub fn from_article_with(article: &str, p: &Parser) -> Result<StudyBook> {
         let mut backlog_w = HashMap::new();
-        let mut backlog_s = HashMap::new();
+        let mut backlog_s: SentenceEntryMap = HashMap::new();
 
-        // Start parsing
-        let p = Parser::new();
+        // Check the markups first
+        let diagnostics = p.validate(article);
+        if !diagnostics.is_empty() {
+            return Err(Error::Markup(diagnostics));
+        }
 
         // Get iter of sentences
         let s_iter = p.cap_sentences_iter(article);
@@ -48,48 +102,83 @@ impl StudyBook {
 
                 // Build the entry
                 let word = Word::from(f_iter.next().unwrap());
-                wordentry_ids.push(word.id()); // Add entry's id to the relevant stentence struct
+                if !wordentry_ids.contains(&word.id()) {
+                    wordentry_ids.push(word.id()); // Add entry's id to the relevant stentence struct
+                }
 
                 let hiragana = f_iter.next().unwrap().to_owned();
 
                 let annotation = f_iter.next().map(String::from);
 
-                // Insert the word entry into the word backlog
-                backlog_w.insert(
-                    word.id(),
-                    WordEntry {
-                        word,
-                        hiragana,
-                        annotation,
-                        sentence_id: clean_s.id(),
-                        level: 1,
-                    },
-                );
+                // Insert the word entry into the word backlog,
+                //  or link the known word to one more sentence
+                match backlog_w.get_mut(&word.id()) {
+                    Some(entry) => {
+                        let entry: &mut WordEntry = entry;
+                        if entry.word.word() != word.word() {
+                            return Err(Error::IdCollision(
+                                entry.word.word().to_owned(),
+                                word.word().to_owned(),
+                            ));
+                        }
+                        entry.add_sentence(clean_s.id());
+                        if entry.annotation.is_none() {
+                            entry.annotation = annotation;
+                        }
+                    }
+                    None => {
+                        backlog_w.insert(
+                            word.id(),
+                            WordEntry {
+                                word,
+                                hiragana,
+                                annotation,
+                                sentence_ids: vec![clean_s.id()],
+                                level: 1,
+                                schedule: Schedule::default(),
+                            },
+                        );
+                    }
+                }
             }
 
             // Insert the sentence entry into the sentence backlog
+            if let Some(other) = backlog_s.get(&clean_s.id()) {
+                if other.sentence.id_src().content() != clean_s.id_src().content() {
+                    return Err(Error::IdCollision(
+                        other.sentence.sentence().to_owned(),
+                        clean_s.sentence().to_owned(),
+                    ));
+                }
+            }
             backlog_s.insert(
                 clean_s.id(),
                 SentenceEntry {
-                    backlog_volumn: wordentry_ids.len() as u8,
+                    backlog_volumn: wordentry_ids.len(),
                     sentence: clean_s,
                     wordentry_ids,
                 },
             );
         }
 
-        let no_word = backlog_w.len() == 0;
+        Ok(StudyBook::from_backlog(p.language(), backlog_w, backlog_s))
+    }
+
+    // A book of new words and their sentences, all in the backlog.
+    fn from_backlog(language: Lang, words: WordEntryMap, sentences: SentenceEntryMap) -> StudyBook {
+        let no_word = words.is_empty();
 
         StudyBook {
+            format_version: migrate::FORMAT_VERSION,
+            language,
             words: StudyObjectCollection {
                 achived: None,
-                backlog: if no_word { None } else { Some(backlog_w) },
+                backlog: if no_word { None } else { Some(words) },
             },
             sentences: StudyObjectCollection {
                 achived: None,
-                backlog: if no_word { None } else { Some(backlog_s) },
+                backlog: if no_word { None } else { Some(sentences) },
             },
-            // status: None,
         }
     }
 
@@ -109,100 +198,367 @@ impl StudyBook {
         }
     }
 
-    pub fn merge<F>(book1: StudyBook, book2: StudyBook, cb: Option<F>) -> StudyBook
-    where
-        F: Fn(Status, Status),
-    {
-        fn merge_map<K: Hash + Eq, V>(
-            map1: Option<HashMap<K, V>>,
-            map2: Option<HashMap<K, V>>,
-        ) -> Option<HashMap<K, V>> {
-            let mut new_map: HashMap<K, V> = HashMap::new();
+    pub fn no_words_in_backlog(&self) -> bool {
+        self.words.backlog.is_none()
+    }
+
+    pub fn backlog_word_ids(&self) -> Vec<String> {
+        match &self.words.backlog {
+            Some(m) => m.keys().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
 
-            if let Some(m1) = map1 {
-                for (k, v) in m1 {
-                    new_map.insert(k, v);
-                }
+    // Look up a word entry in the backlog first, then in the achived collection.
+    pub fn word(&self, id: &str) -> Option<&WordEntry> {
+        [&self.words.backlog, &self.words.achived]
+            .into_iter()
+            .flatten()
+            .find_map(|m| m.get(id))
+    }
+
+    pub fn word_mut(&mut self, id: &str) -> Option<&mut WordEntry> {
+        if let Some(entry) = self.words.backlog.as_mut().and_then(|m| m.get_mut(id)) {
+            return Some(entry);
+        }
+
+        self.words.achived.as_mut().and_then(|m| m.get_mut(id))
+    }
+
+    pub fn sentence(&self, id: &str) -> Option<&SentenceEntry> {
+        [&self.sentences.backlog, &self.sentences.achived]
+            .into_iter()
+            .flatten()
+            .find_map(|m| m.get(id))
+    }
+
+    // All the word entries with their ids, the ones in the backlog first.
+    pub fn words_iter(&self) -> impl Iterator<Item = (&String, &WordEntry)> {
+        [&self.words.backlog, &self.words.achived]
+            .into_iter()
+            .flatten()
+            .flat_map(|m| m.iter())
+    }
+
+    // Find the words whose word, reading or annotation contains the query.
+    pub fn search(&self, query: &str) -> Vec<(&String, &WordEntry)> {
+        let language = self.language.profile();
+        let reading_query = language.normalize(query);
+        let query = query.trim().to_lowercase();
+
+        // Words and readings are compared as readings, annotations as plain text
+        let mut found: Vec<(&String, &WordEntry)> = self
+            .words_iter()
+            .filter(|(_, e)| {
+                [e.word.word(), e.hiragana.as_str()]
+                    .into_iter()
+                    .any(|field| language.normalize(field).contains(&reading_query))
+                    || e.annotation
+                        .as_deref()
+                        .is_some_and(|a| a.to_lowercase().contains(&query))
+            })
+            .collect();
+
+        found.sort_by_key(|(_, e)| e.word.word());
+        found
+    }
+
+    pub fn sentence_mut(&mut self, id: &str) -> Option<&mut SentenceEntry> {
+        if let Some(entry) = self.sentences.backlog.as_mut().and_then(|m| m.get_mut(id)) {
+            return Some(entry);
+        }
+
+        self.sentences.achived.as_mut().and_then(|m| m.get_mut(id))
+    }
+
+    // Make every word refer to exactly the sentences listing it.
+    // Return the ids of the words linked to new sentences.
+    fn link_sentences(&mut self) -> Vec<String> {
+        let mut links: Vec<(String, String)> = [&self.sentences.backlog, &self.sentences.achived]
+            .into_iter()
+            .flatten()
+            .flat_map(|m| m.iter())
+            .flat_map(|(s_id, s)| {
+                s.wordentry_ids
+                    .iter()
+                    .map(move |w_id| (w_id.to_owned(), s_id.to_owned()))
+            })
+            .collect();
+        links.sort();
+
+        for m in [&mut self.words.backlog, &mut self.words.achived]
+            .into_iter()
+            .flatten()
+        {
+            for (w_id, entry) in m.iter_mut() {
+                entry.sentence_ids.retain(|s_id| {
+                    links
+                        .binary_search(&(w_id.to_owned(), s_id.to_owned()))
+                        .is_ok()
+                });
             }
+        }
 
-            if let Some(m2) = map2 {
-                for (k, v) in m2 {
-                    new_map.insert(k, v);
+        let mut linked = Vec::new();
+
+        for (w_id, s_id) in links {
+            if let Some(entry) = self.word_mut(&w_id) {
+                if entry.add_sentence(s_id) && !linked.contains(&w_id) {
+                    linked.push(w_id);
                 }
             }
+        }
 
-            match new_map.len() {
-                0 => None,
-                _ => Some(new_map),
+        linked
+    }
+
+    // Recount the backlog volume of every sentence and move the sentences
+    // whose words are all archived to the achived collection, and vice versa.
+    fn refresh_sentences(&mut self) {
+        let ids: Vec<String> = [&self.sentences.backlog, &self.sentences.achived]
+            .into_iter()
+            .flatten()
+            .flat_map(|m| m.keys().cloned())
+            .collect();
+
+        for id in ids {
+            let (was_archived, should_archive) = {
+                let backlog_w = &self.words.backlog;
+                let was_archived =
+                    matches!(&self.sentences.achived, Some(m) if m.contains_key(&id));
+                let s = match [
+                    self.sentences.backlog.as_mut(),
+                    self.sentences.achived.as_mut(),
+                ]
+                .into_iter()
+                .flatten()
+                .find_map(|m| m.get_mut(&id))
+                {
+                    Some(s) => s,
+                    None => continue,
+                };
+
+                s.backlog_volumn = s
+                    .wordentry_ids
+                    .iter()
+                    .filter(|w_id| matches!(backlog_w, Some(m) if m.contains_key(*w_id)))
+                    .count();
+
+                (
+                    was_archived,
+                    !s.wordentry_ids.is_empty() && s.backlog_volumn == 0,
+                )
+            };
+
+            if was_archived && !should_archive {
+                if let Some(s) = take_entry(&mut self.sentences.achived, &id) {
+                    put_entry(&mut self.sentences.backlog, id, s);
+                }
+            } else if !was_archived && should_archive {
+                if let Some(s) = take_entry(&mut self.sentences.backlog, &id) {
+                    put_entry(&mut self.sentences.achived, id, s);
+                }
             }
         }
+    }
 
-        let s_add = book2.get_status();
+    // Ids of the words due before the end of the day of `now`, most overdue first.
+    pub fn due_today(&self, now: u64) -> Vec<String> {
+        let deadline = end_of_day(now);
+        let mut due: Vec<(&String, &WordEntry)> = [&self.words.backlog, &self.words.achived]
+            .into_iter()
+            .flatten()
+            .flat_map(|m| m.iter())
+            .filter(|(_, e)| e.schedule.due < deadline)
+            .collect();
+
+        due.sort_by_key(|(id, e)| (e.schedule.due, id.to_owned()));
+        due.into_iter().map(|(id, _)| id.to_owned()).collect()
+    }
 
-        let new_book = StudyBook {
-            words: StudyObjectCollection {
-                achived: merge_map(book1.words.achived, book2.words.achived),
-                backlog: merge_map(book1.words.backlog, book2.words.backlog),
-            },
-            sentences: StudyObjectCollection {
-                achived: merge_map(book1.sentences.achived, book2.sentences.achived),
-                backlog: merge_map(book1.sentences.backlog, book2.sentences.backlog),
-            },
-        };
+    // Grade the answer for the word's reading and reschedule it:
+    //  - Correct -> level up, next review further away
+    //  - Wrong -> back to level 1, review again tomorrow
+    // Return None if the word doesn't exist.
+    pub fn review_word(&mut self, id: &str, answer: &str, now: u64) -> Option<bool> {
+        let language = self.language;
+        let correct = self.word(id)?.check_reading(answer, language.profile());
 
-        let s_new = new_book.get_status();
+        self.grade_word(id, if correct { Grade::Good } else { Grade::Again }, now)?;
 
-        if let Some(cb) = cb {
-            cb(s_add, s_new);
+        Some(correct)
+    }
+
+    // Record a review graded by a quiz, and archive the word once mastered,
+    //  or bring it back if it's failed.
+    // Return None if the word doesn't exist.
+    pub fn grade_word(&mut self, id: &str, grade: Grade, now: u64) -> Option<()> {
+        let entry = self.word_mut(id)?;
+        entry.review(grade, now);
+        let level = entry.level;
+
+        if grade == Grade::Again {
+            self.unarchive_word(id);
+        } else if level >= MASTERY_LEVEL {
+            self.archive_word(id);
         }
 
-        new_book
+        Some(())
     }
 
-    pub fn no_words_in_backlog(&self) -> bool {
-        match &self.words.backlog {
-            None => true,
-            _ => false,
-        }
+    pub fn is_archived(&self, id: &str) -> bool {
+        matches!(&self.words.achived, Some(m) if m.contains_key(id))
     }
 
-    pub fn to_json(&self) -> Result<String, &'static str> {
-        match serde_json::to_string(self) {
-            Ok(json) => Ok(json),
-            Err(_) => Err("Failed to convert the book into json"),
+    // Move the word from the backlog to the achived collection.
+    // Each of its sentences gets archived as well once it has no word left in the backlog.
+    // Return false if the word isn't in the backlog.
+    pub fn archive_word(&mut self, id: &str) -> bool {
+        let entry = match take_entry(&mut self.words.backlog, id) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let s_ids = entry.sentence_ids.to_owned();
+        put_entry(&mut self.words.achived, id.to_owned(), entry);
+
+        for s_id in s_ids {
+            if let Some(s) = self
+                .sentences
+                .backlog
+                .as_mut()
+                .and_then(|m| m.get_mut(&s_id))
+            {
+                s.backlog_volumn = s.backlog_volumn.saturating_sub(1);
+
+                if s.backlog_volumn == 0 {
+                    if let Some(s) = take_entry(&mut self.sentences.backlog, &s_id) {
+                        put_entry(&mut self.sentences.achived, s_id, s);
+                    }
+                }
+            }
         }
+
+        true
     }
 
-    pub fn save_json(&self, path: &str) -> Result<(), &'static str> {
-        match self.to_json() {
-            Ok(json) => match fs::write(path, json) {
-                Ok(_) => Ok(()),
-                Err(_) => Err("Failed to save the book in the target path."),
-            },
-            Err(msg) => Err(msg),
+    // The reverse of archive_word: a mastered word relapses into the backlog,
+    // bringing its sentences back with it.
+    // Return false if the word isn't archived.
+    pub fn unarchive_word(&mut self, id: &str) -> bool {
+        let entry = match take_entry(&mut self.words.achived, id) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let s_ids = entry.sentence_ids.to_owned();
+        put_entry(&mut self.words.backlog, id.to_owned(), entry);
+
+        for s_id in s_ids {
+            if let Some(s) = take_entry(&mut self.sentences.achived, &s_id) {
+                put_entry(&mut self.sentences.backlog, s_id.to_owned(), s);
+            }
+
+            if let Some(s) = self
+                .sentences
+                .backlog
+                .as_mut()
+                .and_then(|m| m.get_mut(&s_id))
+            {
+                s.backlog_volumn = s.backlog_volumn.saturating_add(1);
+            }
         }
+
+        true
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(Error::Serialize)
+    }
+
+    // Save the book without ever leaving it half written, backing up the previous one now and then.
+    pub fn save_json(&self, path: &str) -> Result<()> {
+        let json = self.to_json()?;
+
+        backup::back_up(path, schedule::now(), false)?;
+        backup::write_atomic(path, &json)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::config::ParserConfig;
+    use std::fs;
 
     const ARTICLE: &str = r"ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>まる<<中・なか>>、日本の<<自動車・じどうしゃ>>メーカーに<<影響・えいきょう・>>が<<広がっています・ひろがる・to spread out>>。トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。";
 
-    const A_1: &str = r"ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>まる<<中・なか>>、日本の<<自動車・じどうしゃ>>メーカーに<<影響・えいきょう・>>が<<広がっています・ひろがる・to spread out>>。";
+    const NOW: u64 = 1_650_000_000;
+
     const A_2: &str = r"トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。";
 
     #[test]
     fn can_detect_no_word_in_backlog() {
-        let b = StudyBook::from_article("へへへへへへ");
+        let b = StudyBook::from_article("へへへへへへ").unwrap();
 
         assert!(b.no_words_in_backlog());
     }
 
+    #[test]
+    fn can_reject_invalid_markups() {
+        match StudyBook::from_article("ロシアにある<<工場>>の<<稼働・かどう") {
+            Err(Error::Markup(diagnostics)) => assert_eq!(diagnostics.len(), 2),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn can_gen_book_from_questions_and_dialogues() {
+        let b = StudyBook::from_article(
+            "<<工場・こうじょう>>はどこ？\n「<<停止・ていし>>します。」と言った",
+        )
+        .unwrap();
+
+        let sentences: Vec<&str> = ["工場", "停止"]
+            .iter()
+            .map(|w| {
+                let entry = b.word(&Word::from(w).id()).unwrap();
+                b.sentence(&entry.sentence_ids[0])
+                    .unwrap()
+                    .sentence
+                    .sentence()
+            })
+            .collect();
+
+        assert_eq!(
+            sentences,
+            vec!["`工場`はどこ？", "「`停止`します。」と言った。"]
+        );
+    }
+
+    #[test]
+    fn can_gen_book_with_custom_syntax() {
+        let p = Parser::with_config(ParserConfig::ruby()).unwrap();
+        let b = StudyBook::from_article_with(
+            "ロシアにある工場(こうじょう)の稼働(かどう・running)を。",
+            &p,
+        )
+        .unwrap();
+
+        let entry = b.word(&Word::from("稼働").id()).unwrap();
+        assert_eq!(entry.hiragana, "かどう");
+        assert_eq!(entry.annotation.as_deref(), Some("running"));
+        assert_eq!(b.language, Lang::Japanese);
+        assert_eq!(
+            b.sentence(&entry.sentence_ids[0])
+                .unwrap()
+                .sentence
+                .sentence(),
+            "ロシアにある`工場`の`稼働`を。"
+        );
+    }
+
     #[test]
     fn can_gen_book_from_article() {
-        let b = StudyBook::from_article(ARTICLE);
+        let b = StudyBook::from_article(ARTICLE).unwrap();
 
         let backlog_w = b.words.backlog.unwrap();
         let backlog_s = b.sentences.backlog.unwrap();
@@ -221,7 +577,7 @@ mod tests {
             "operation of a machine, running"
         );
 
-        let entry_s = backlog_s.get(&entry_w.sentence_id).unwrap();
+        let entry_s = backlog_s.get(&entry_w.sentence_ids[0]).unwrap();
         assert_eq!(
             entry_s.sentence.sentence(),
             "トヨタ自動車はあすからロシアにある`工場`の`稼働`を`停止`すると`発表`しました。"
@@ -234,9 +590,31 @@ mod tests {
         assert_eq!(entry_w.annotation, None);
     }
 
+    #[test]
+    fn can_link_word_to_many_sentences() {
+        let b = StudyBook::from_article(
+            r"新しい<<工場・こうじょう>>。古い<<工場・こうじょう・factory>>と<<工場・こうじょう>>。",
+        ).unwrap();
+
+        let s = b.get_status();
+        assert_eq!(s.w_backlog, 1);
+        assert_eq!(s.s_backlog, 2);
+
+        let entry = b.word(&Word::from("工場").id()).unwrap();
+        assert_eq!(entry.sentence_ids.len(), 2);
+        assert_eq!(entry.annotation.as_deref(), Some("factory"));
+
+        for s_id in &entry.sentence_ids {
+            assert_eq!(
+                b.sentence(s_id).unwrap().wordentry_ids,
+                vec![entry.word.id()]
+            );
+        }
+    }
+
     #[test]
     fn can_report_correct_status() {
-        let s = StudyBook::from_article(ARTICLE).get_status();
+        let s = StudyBook::from_article(ARTICLE).unwrap().get_status();
 
         assert_eq!(s.s_archived, 0);
         assert_eq!(s.w_archived, 0);
@@ -244,33 +622,14 @@ mod tests {
         assert_eq!(s.w_backlog, 10);
     }
 
-    #[test]
-    fn can_merge_books() {
-        let b1 = StudyBook::from_article(A_1);
-        let b2 = StudyBook::from_article(A_2);
-
-        StudyBook::merge(
-            b1,
-            b2,
-            Some(|s_add: Status, s_new: Status| {
-                assert_eq!(s_add.s_archived, 0);
-                assert_eq!(s_add.w_archived, 0);
-                assert_eq!(s_add.s_backlog, 1);
-                assert_eq!(s_add.w_backlog, 4);
-
-                assert_eq!(s_new.s_archived, 0);
-                assert_eq!(s_new.w_archived, 0);
-                assert_eq!(s_new.s_backlog, 2);
-                assert_eq!(s_new.w_backlog, 10);
-            }),
-        );
-    }
-
     #[test]
     fn can_save_json() {
-        let path = ".test/test.json";
+        // Saving backs the book up next to it, so it gets a directory of its own
+        let dir = ".test/save_json";
+        let _ = fs::remove_dir_all(dir);
+        let path = &format!("{}/test.json", dir);
         let mini_article = r"ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>。";
-        let mini_book = StudyBook::from_article(mini_article);
+        let mini_book = StudyBook::from_article(mini_article).unwrap();
         mini_book.save_json(path).unwrap();
 
         let saved_book = fs::read_to_string(path).unwrap();
@@ -282,5 +641,137 @@ mod tests {
         assert_eq!(s.w_archived, 0);
         assert_eq!(s.s_backlog, 1);
         assert_eq!(s.w_backlog, 2);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn can_review_word() {
+        let mut b = StudyBook::from_article(A_2).unwrap();
+        let id = Word::from("工場").id();
+
+        assert_eq!(b.review_word(&id, "こうじょう", NOW), Some(true));
+        assert_eq!(b.review_word(&id, "こうじょう", NOW), Some(true));
+        assert_eq!(b.word(&id).unwrap().level, 3);
+        assert_eq!(b.word(&id).unwrap().schedule.reviews, 2);
+
+        assert_eq!(b.review_word(&id, "こうば", NOW), Some(false));
+        assert_eq!(b.word(&id).unwrap().level, 1);
+
+        assert_eq!(b.review_word("ghost", "こうば", NOW), None);
+    }
+
+    #[test]
+    fn can_archive_and_relapse_words() {
+        let mut b = StudyBook::from_article(A_2).unwrap();
+        let w_1 = Word::from("工場").id();
+        let w_2 = Word::from("稼働").id();
+        let s_id = b.word(&w_1).unwrap().sentence_ids[0].to_owned();
+
+        assert!(b.archive_word(&w_1));
+        assert!(!b.archive_word(&w_1));
+        assert!(b.is_archived(&w_1));
+        assert_eq!(b.sentence(&s_id).unwrap().backlog_volumn, 3);
+
+        let s = b.get_status();
+        assert_eq!(s.w_archived, 1);
+        assert_eq!(s.w_backlog, 3);
+        assert_eq!(s.s_archived, 0);
+
+        // The sentence is archived with its last word
+        b.archive_word(&w_2);
+        b.archive_word(&Word::from("停止").id());
+        b.archive_word(&Word::from("発表").id());
+
+        let s = b.get_status();
+        assert_eq!(s.w_archived, 4);
+        assert_eq!(s.s_archived, 1);
+        assert_eq!(s.s_backlog, 0);
+        assert!(b.no_words_in_backlog());
+
+        // Failing a mastered word brings the sentence back
+        assert!(b.unarchive_word(&w_2));
+        assert!(!b.unarchive_word(&w_2));
+        assert_eq!(b.sentence(&s_id).unwrap().backlog_volumn, 1);
+
+        let s = b.get_status();
+        assert_eq!(s.w_archived, 3);
+        assert_eq!(s.w_backlog, 1);
+        assert_eq!(s.s_archived, 0);
+        assert_eq!(s.s_backlog, 1);
+    }
+
+    #[test]
+    fn can_count_long_sentences() {
+        let article: String = (0..300).map(|i| format!("<<語{}・ご>>", i)).collect();
+        let mut b = StudyBook::from_article(&article).unwrap();
+        let s_id = b.word(&Word::from("語0").id()).unwrap().sentence_ids[0].to_owned();
+        assert_eq!(b.sentence(&s_id).unwrap().backlog_volumn, 300);
+
+        // The sentence waits for its last word
+        for i in 1..300 {
+            b.archive_word(&Word::from(format!("語{}", i).as_str()).id());
+        }
+        assert_eq!(b.sentence(&s_id).unwrap().backlog_volumn, 1);
+        assert_eq!(b.get_status().s_backlog, 1);
+    }
+
+    #[test]
+    fn reviews_archive_mastered_words() {
+        let mut b = StudyBook::from_article(A_2).unwrap();
+        let id = Word::from("工場").id();
+
+        for _ in 1..MASTERY_LEVEL {
+            b.review_word(&id, "こうじょう", NOW);
+        }
+        assert!(b.is_archived(&id));
+
+        b.review_word(&id, "こうば", NOW);
+        assert!(!b.is_archived(&id));
+    }
+
+    #[test]
+    fn can_review_words_of_other_languages() {
+        let p = Parser::with_config(ParserConfig::for_language(Lang::Chinese)).unwrap();
+        let mut b = StudyBook::from_article_with("我去<<工厂・gōngchǎng・factory>>。", &p).unwrap();
+        assert_eq!(b.language, Lang::Chinese);
+
+        let id = Word::from("工厂").id();
+        assert_eq!(b.review_word(&id, "Gōng chǎng", NOW), Some(true));
+
+        // The language is saved with the book
+        let b: StudyBook = serde_json::from_str(&b.to_json().unwrap()).unwrap();
+        assert_eq!(b.language, Lang::Chinese);
+    }
+
+    #[test]
+    fn can_search_words() {
+        let b = StudyBook::from_article(ARTICLE).unwrap();
+
+        let words = |query| -> Vec<&str> {
+            b.search(query)
+                .into_iter()
+                .map(|(_, e)| e.word.word())
+                .collect()
+        };
+
+        assert_eq!(words("自動"), vec!["自動車"]);
+        assert_eq!(words("カドウ"), vec!["稼働"]);
+        assert_eq!(words("Spread"), vec!["広がっています"]);
+        assert!(words("飛行機").is_empty());
+    }
+
+    #[test]
+    fn can_query_due_words() {
+        let mut b = StudyBook::from_article(A_2).unwrap();
+        assert_eq!(b.due_today(NOW).len(), 4);
+
+        let id = Word::from("工場").id();
+        b.review_word(&id, "こうじょう", NOW);
+
+        let due = b.due_today(NOW);
+        assert_eq!(due.len(), 3);
+        assert!(!due.contains(&id));
+        assert!(b.due_today(NOW + schedule::DAY).contains(&id));
     }
 }
@@ -0,0 +1,68 @@
+use super::StudyBook;
+
+// Header lines read by Anki's text importer.
+const HEADER: &str =
+    "#separator:tab\n#html:true\n#columns:Text\tWord\tReading\tMeaning\tTags\n#tags column:5\n";
+
+// Fields are separated by tabs and read as html.
+fn escape(field: &str) -> String {
+    field
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+impl StudyBook {
+    // One Anki note per word, as a TSV to import with the Cloze note type:
+    //  the first sentence of the word with the word as the cloze deletion,
+    //  then the word, its reading and annotation, and the level as a tag.
+    pub fn to_anki_tsv(&self) -> String {
+        let mut words: Vec<_> = self.words_iter().map(|(_, e)| e).collect();
+        words.sort_by_key(|e| e.word.word());
+
+        let notes = words.into_iter().map(|e| {
+            let word = e.word.word();
+            let cloze = |w: &str| format!("{{{{c1::{}}}}}", w);
+            let text = match e.sentence_ids.first().and_then(|id| self.sentence(id)) {
+                Some(s) => escape(&s.sentence.cloze(word, cloze, str::to_owned)),
+                None => escape(&cloze(word)),
+            };
+
+            [
+                text,
+                escape(word),
+                escape(&e.hiragana),
+                escape(e.annotation.as_deref().unwrap_or_default()),
+                format!("level::{}", e.level),
+            ]
+            .join("\t")
+        });
+
+        notes.fold(String::from(HEADER), |tsv, note| tsv + &note + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_export_words_as_cloze_notes() {
+        let book = StudyBook::from_article(
+            "ロシアへの<<経済制裁・けいざいせいさい・sanctions>>が<<強・つよ>>まる。",
+        )
+        .unwrap();
+        let tsv = book.to_anki_tsv();
+        let notes: Vec<&str> = tsv.lines().filter(|l| !l.starts_with('#')).collect();
+
+        assert_eq!(
+            notes,
+            vec![
+                "ロシアへの経済制裁が{{c1::強}}まる。\t強\tつよ\t\tlevel::1",
+                "ロシアへの{{c1::経済制裁}}が強まる。\t経済制裁\tけいざいせいさい\tsanctions\tlevel::1",
+            ]
+        );
+    }
+}
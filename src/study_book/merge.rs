@@ -0,0 +1,378 @@
+use super::{put_entry, status::Status, take_entry, word::WordEntry, StudyBook};
+
+pub enum Resolution {
+    KeepExisting,
+    TakeIncoming,
+}
+
+// Given (existing, incoming), decide which entry to keep.
+pub type ConflictCallback = Box<dyn Fn(&WordEntry, &WordEntry) -> Resolution>;
+
+// How to resolve a word that exists in both books with a different reading or annotation.
+pub enum WordPolicy {
+    KeepExisting,
+    // Keep the entry with more progress, the existing one on a tie.
+    PreferHigherLevel,
+    Custom(ConflictCallback),
+}
+
+impl WordPolicy {
+    fn resolve(&self, existing: &WordEntry, incoming: &WordEntry) -> Resolution {
+        match self {
+            WordPolicy::KeepExisting => Resolution::KeepExisting,
+            WordPolicy::PreferHigherLevel => match incoming.level > existing.level {
+                true => Resolution::TakeIncoming,
+                false => Resolution::KeepExisting,
+            },
+            WordPolicy::Custom(cb) => cb(existing, incoming),
+        }
+    }
+}
+
+pub struct MergePolicy {
+    pub words: WordPolicy,
+    // Add the incoming references of known words to sentences found in both books,
+    // otherwise known sentences only get the new words linked to them.
+    pub union_sentences: bool,
+}
+
+impl Default for MergePolicy {
+    fn default() -> Self {
+        MergePolicy {
+            words: WordPolicy::PreferHigherLevel,
+            union_sentences: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MergeReport {
+    pub added: Status,
+    pub total: Status,
+    pub new_words: Vec<String>,
+    // Words whose missing annotation got filled in, or linked to new sentences.
+    pub updated_words: Vec<String>,
+    pub conflicts: Vec<String>,
+    // Entries left out as they have the same id as a different existing one.
+    pub collisions: Vec<String>,
+    pub new_sentences: Vec<String>,
+    // Sentences which got new word references.
+    pub updated_sentences: Vec<String>,
+}
+
+// None and "" both mean no annotation.
+fn annotation(entry: &WordEntry) -> Option<&str> {
+    entry.annotation.as_deref().filter(|a| !a.is_empty())
+}
+
+impl StudyBook {
+    // Merge book2 into book1 without losing the progress made in book1.
+    // The merged book keeps the language of book1:
+    //  - New entries are added as they are
+    //  - Known words keep their level, schedule and sentence
+    //  - Conflicting words are resolved by the policy
+    pub fn merge(
+        book1: StudyBook,
+        book2: StudyBook,
+        policy: &MergePolicy,
+    ) -> (StudyBook, MergeReport) {
+        let before = book1.get_status();
+        let mut book = book1;
+        let mut report = MergeReport::default();
+
+        // Merge words
+        let incoming = [(book2.words.backlog, false), (book2.words.achived, true)];
+
+        for (map, archived) in incoming {
+            for (id, mut entry) in map.into_iter().flatten() {
+                let take_incoming = match book.word_mut(&id) {
+                    None => {
+                        report.new_words.push(id.to_owned());
+                        true
+                    }
+                    // A different word of the same id is left out
+                    Some(existing) if existing.word.word() != entry.word.word() => {
+                        report.collisions.push(id.to_owned());
+                        false
+                    }
+                    Some(existing) => {
+                        if existing.hiragana != entry.hiragana
+                            || matches!((annotation(existing), annotation(&entry)), (Some(a1), Some(a2)) if a1 != a2)
+                        {
+                            report.conflicts.push(id.to_owned());
+                            matches!(
+                                policy.words.resolve(existing, &entry),
+                                Resolution::TakeIncoming
+                            )
+                        } else {
+                            if annotation(existing).is_none() && annotation(&entry).is_some() {
+                                existing.annotation = entry.annotation.clone();
+                                report.updated_words.push(id.to_owned());
+                            }
+                            false
+                        }
+                    }
+                };
+
+                if take_incoming {
+                    // Never lose the contexts of the replaced entry
+                    let replaced = take_entry(&mut book.words.backlog, &id)
+                        .or_else(|| take_entry(&mut book.words.achived, &id));
+
+                    for s_id in replaced.into_iter().flat_map(|e| e.sentence_ids) {
+                        entry.add_sentence(s_id);
+                    }
+
+                    match archived {
+                        true => put_entry(&mut book.words.achived, id, entry),
+                        false => put_entry(&mut book.words.backlog, id, entry),
+                    }
+                }
+            }
+        }
+
+        // Merge sentences
+        let incoming = [book2.sentences.backlog, book2.sentences.achived];
+
+        for (id, mut entry) in incoming.into_iter().flatten().flatten() {
+            match book.sentence_mut(&id) {
+                None => {
+                    entry
+                        .wordentry_ids
+                        .retain(|w_id| !report.collisions.contains(w_id));
+                    report.new_sentences.push(id.to_owned());
+                    put_entry(&mut book.sentences.backlog, id, entry);
+                }
+                Some(existing)
+                    if existing.sentence.id_src().content()
+                        != entry.sentence.id_src().content() =>
+                {
+                    report.collisions.push(id);
+                }
+                Some(existing) => {
+                    let before = existing.wordentry_ids.len();
+
+                    for w_id in entry.wordentry_ids {
+                        // A new word always gets its sentence
+                        let wanted = policy.union_sentences || report.new_words.contains(&w_id);
+
+                        if wanted
+                            && !existing.wordentry_ids.contains(&w_id)
+                            && !report.collisions.contains(&w_id)
+                        {
+                            existing.wordentry_ids.push(w_id);
+                        }
+                    }
+
+                    if existing.wordentry_ids.len() > before {
+                        report.updated_sentences.push(id);
+                    }
+                }
+            }
+        }
+
+        // Link the words to their new sentences
+        for id in book.link_sentences() {
+            if !report.new_words.contains(&id) && !report.updated_words.contains(&id) {
+                report.updated_words.push(id);
+            }
+        }
+
+        book.refresh_sentences();
+
+        report.total = book.get_status();
+        report.added = report.total.since(&before);
+
+        (book, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::study_book::{sentence::Sentence, word::Word};
+
+    const NOW: u64 = 1_650_000_000;
+
+    const A_1: &str = r"ロシアへの<<経済制裁・けいざいせいさい>>が<<強・つよ>>まる<<中・なか>>、日本の<<自動車・じどうしゃ>>メーカーに<<影響・えいきょう・>>が<<広がっています・ひろがる・to spread out>>。";
+    const A_2: &str = r"トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。";
+
+    #[test]
+    fn can_merge_books() {
+        let b1 = StudyBook::from_article(A_1).unwrap();
+        let b2 = StudyBook::from_article(A_2).unwrap();
+
+        let (_, r) = StudyBook::merge(b1, b2, &MergePolicy::default());
+
+        assert_eq!(r.added.s_archived, 0);
+        assert_eq!(r.added.w_archived, 0);
+        assert_eq!(r.added.s_backlog, 1);
+        assert_eq!(r.added.w_backlog, 4);
+
+        assert_eq!(r.total.s_archived, 0);
+        assert_eq!(r.total.w_archived, 0);
+        assert_eq!(r.total.s_backlog, 2);
+        assert_eq!(r.total.w_backlog, 10);
+
+        assert_eq!(r.new_words.len(), 4);
+        assert_eq!(r.new_sentences.len(), 1);
+        assert!(r.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_leaves_out_colliding_entries() {
+        let b1 = StudyBook::from_article(A_2).unwrap();
+        let id = Word::from("工場").id();
+
+        // Pretend another word got the same id
+        let mut b2 = StudyBook::from_article(r"新しい<<工廠・こうしょう>>。").unwrap();
+        let backlog = b2.words.backlog.as_mut().unwrap();
+        let entry = backlog.remove(&Word::from("工廠").id()).unwrap();
+        backlog.insert(id.to_owned(), entry);
+        for s in b2.sentences.backlog.as_mut().unwrap().values_mut() {
+            s.wordentry_ids = vec![id.to_owned()];
+        }
+
+        let (b, r) = StudyBook::merge(b1, b2, &MergePolicy::default());
+
+        assert_eq!(r.collisions, vec![id.to_owned()]);
+        assert_eq!(b.word(&id).unwrap().word.word(), "工場");
+        assert_eq!(b.word(&id).unwrap().sentence_ids.len(), 1);
+        assert!(r.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_progress_of_known_words() {
+        let mut b1 = StudyBook::from_article(A_2).unwrap();
+        let id = Word::from("工場").id();
+        b1.review_word(&id, "こうじょう", NOW);
+        b1.review_word(&id, "こうじょう", NOW);
+
+        let b2 = StudyBook::from_article(r"新しい<<工場・こうじょう>>。").unwrap();
+        let (b, r) = StudyBook::merge(b1, b2, &MergePolicy::default());
+
+        let entry = b.word(&id).unwrap();
+        assert_eq!(entry.level, 3);
+        assert_eq!(entry.schedule.reviews, 2);
+        assert_eq!(entry.sentence_ids.len(), 2);
+        assert_eq!(
+            b.sentence(&entry.sentence_ids[0])
+                .unwrap()
+                .wordentry_ids
+                .len(),
+            4
+        );
+
+        assert!(r.new_words.is_empty());
+        assert_eq!(r.new_sentences.len(), 1);
+        assert_eq!(r.added.w_backlog, 0);
+        assert_eq!(r.added.s_backlog, 1);
+    }
+
+    #[test]
+    fn merge_can_leave_known_words_out_of_known_sentences() {
+        let known = r"ロシアにある<<工場・こうじょう>>。<<ロシア・ろしあ>>の冬。";
+        let incoming = r"<<ロシア・ろしあ>>に<<ある・ある>><<工場・こうじょう>>。";
+        let (russia, aru) = (Word::from("ロシア").id(), Word::from("ある").id());
+        let s_id = Sentence::from("ロシアにある工場。").id();
+        let winter = Sentence::from("ロシアの冬。").id();
+        let policy = MergePolicy {
+            words: WordPolicy::KeepExisting,
+            union_sentences: false,
+        };
+
+        // The known word keeps its sentences, the new one is linked to its sentence
+        let b1 = StudyBook::from_article(known).unwrap();
+        let (b, r) = StudyBook::merge(b1, StudyBook::from_article(incoming).unwrap(), &policy);
+        assert_eq!(b.sentence(&s_id).unwrap().wordentry_ids.len(), 2);
+        assert_eq!(
+            b.word(&russia).unwrap().sentence_ids,
+            vec![winter.to_owned()]
+        );
+        assert_eq!(b.word(&aru).unwrap().sentence_ids, vec![s_id.to_owned()]);
+        assert_eq!(r.updated_sentences, vec![s_id.to_owned()]);
+
+        let b1 = StudyBook::from_article(known).unwrap();
+        let (b, r) = StudyBook::merge(
+            b1,
+            StudyBook::from_article(incoming).unwrap(),
+            &MergePolicy::default(),
+        );
+        assert_eq!(b.sentence(&s_id).unwrap().wordentry_ids.len(), 3);
+        assert_eq!(
+            b.word(&russia).unwrap().sentence_ids,
+            vec![winter, s_id.to_owned()]
+        );
+        assert_eq!(r.updated_sentences, vec![s_id]);
+    }
+
+    #[test]
+    fn merge_fills_in_missing_annotation() {
+        let b1 = StudyBook::from_article(A_2).unwrap();
+        let b2 = StudyBook::from_article(r"ロシアにある<<工場・こうじょう・factory>>。").unwrap();
+        let (b, r) = StudyBook::merge(b1, b2, &MergePolicy::default());
+
+        let id = Word::from("工場").id();
+        assert_eq!(r.updated_words, vec![id.to_owned()]);
+        assert_eq!(b.word(&id).unwrap().annotation.as_deref(), Some("factory"));
+    }
+
+    #[test]
+    fn merge_resolves_conflicts_by_policy() {
+        let id = Word::from("工場").id();
+        let conflicting = r"<<工場・こうば>>。";
+
+        // Existing entry has more progress
+        let mut b1 = StudyBook::from_article(A_2).unwrap();
+        b1.review_word(&id, "こうじょう", NOW);
+        let (b, r) = StudyBook::merge(
+            b1,
+            StudyBook::from_article(conflicting).unwrap(),
+            &MergePolicy::default(),
+        );
+        assert_eq!(r.conflicts, vec![id.to_owned()]);
+        assert_eq!(b.word(&id).unwrap().hiragana, "こうじょう");
+
+        // Same progress, existing one wins on a tie
+        let b1 = StudyBook::from_article(A_2).unwrap();
+        let (b, _) = StudyBook::merge(
+            b1,
+            StudyBook::from_article(conflicting).unwrap(),
+            &MergePolicy::default(),
+        );
+        assert_eq!(b.word(&id).unwrap().hiragana, "こうじょう");
+
+        // Callback decides
+        let policy = MergePolicy {
+            words: WordPolicy::Custom(Box::new(|_, _| Resolution::TakeIncoming)),
+            union_sentences: false,
+        };
+        let b1 = StudyBook::from_article(A_2).unwrap();
+        let (b, r) = StudyBook::merge(b1, StudyBook::from_article(conflicting).unwrap(), &policy);
+        assert_eq!(r.conflicts, vec![id.to_owned()]);
+        assert_eq!(b.word(&id).unwrap().hiragana, "こうば");
+    }
+
+    #[test]
+    fn merge_keeps_archived_words_archived() {
+        let mut b1 = StudyBook::from_article(A_2).unwrap();
+        let id = Word::from("工場").id();
+        b1.archive_word(&id);
+
+        let (b, r) = StudyBook::merge(
+            b1,
+            StudyBook::from_article(A_2).unwrap(),
+            &MergePolicy::default(),
+        );
+
+        assert!(b.is_archived(&id));
+        assert_eq!(r.total.w_archived, 1);
+        assert_eq!(r.total.w_backlog, 3);
+        assert_eq!(
+            b.sentence(&b.word(&id).unwrap().sentence_ids[0])
+                .unwrap()
+                .backlog_volumn,
+            3
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use super::{schedule::Schedule, sentence::Sentence, word::Word, MASTERY_LEVEL};
+use crate::language::Lang;
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+
+// The version of the book layout written by this build.
+// Bump it along with a new step in STEPS when the layout changes.
+pub const FORMAT_VERSION: u64 = 4;
+
+// STEPS[v] upgrades a book of version v to v + 1.
+const STEPS: [fn(Value) -> Value; FORMAT_VERSION as usize] =
+    [from_wordbook, from_unversioned, hash_ids, count_repetitions];
+
+// Version 0 is the legacy wordbook: all the words under `entries`,
+//  sentences listing their `entry_ids`.
+// Version 1 covers the StudyBook layouts with no format_version yet. Along the way words
+//  got a `schedule`, then many sentences (`sentence_ids` for the single `sentence_id`),
+//  and books a `language`. A book of version 1 may lack any of them.
+// Version 2 has all of them and the format_version, entries are keyed by the base64
+//  of their content.
+// Version 3 keys the entries by a hash of their content, see IdSrc.
+// Version 4 counts the reviews passed since the last lapse in the schedules.
+pub fn version_of(book: &Value) -> u64 {
+    match book.get("format_version").and_then(Value::as_u64) {
+        Some(version) => version,
+        None if book.get("entries").is_some() => 0,
+        None => 1,
+    }
+}
+
+// Upgrade a book of any older layout to the current one.
+// Fails with the version of a book written by a newer build.
+pub fn upgrade(mut book: Value) -> Result<Value, u64> {
+    let version = version_of(&book);
+    if version > FORMAT_VERSION {
+        return Err(version);
+    }
+
+    for step in &STEPS[version as usize..] {
+        book = step(book);
+    }
+
+    Ok(book)
+}
+
+// Split the words of the wordbook into backlog and archive by level,
+//  and count the words left to learn in every sentence.
+fn from_wordbook(mut book: Value) -> Value {
+    let entries = match book.get_mut("entries").map(Value::take) {
+        Some(Value::Object(entries)) => entries,
+        _ => Map::new(),
+    };
+    let sentences = match book.get_mut("sentences").map(Value::take) {
+        Some(Value::Object(sentences)) => sentences,
+        _ => Map::new(),
+    };
+
+    let mastered = |entry: &Value| {
+        entry
+            .get("level")
+            .and_then(Value::as_u64)
+            .is_some_and(|level| level >= MASTERY_LEVEL as u64)
+    };
+
+    let (mut words_achived, mut words_backlog) = (Map::new(), Map::new());
+    for (id, entry) in entries.iter() {
+        match mastered(entry) {
+            true => words_achived.insert(id.to_owned(), entry.to_owned()),
+            false => words_backlog.insert(id.to_owned(), entry.to_owned()),
+        };
+    }
+
+    let (mut sentences_achived, mut sentences_backlog) = (Map::new(), Map::new());
+    for (id, mut sentence) in sentences {
+        let ids = sentence
+            .get_mut("entry_ids")
+            .map(Value::take)
+            .unwrap_or(json!([]));
+        let linked = ids.as_array().is_some_and(|ids| !ids.is_empty());
+        let volumn = ids.as_array().map_or(0, |ids| {
+            ids.iter()
+                .filter_map(Value::as_str)
+                .filter(|id| words_backlog.contains_key(*id))
+                .count()
+        });
+
+        if let Some(sentence) = sentence.as_object_mut() {
+            sentence.remove("entry_ids");
+            sentence.insert(String::from("wordentry_ids"), ids);
+            sentence.insert(String::from("backlog_volumn"), json!(volumn));
+        }
+
+        // Archived once all its words are, as refresh_sentences does
+        match linked && volumn == 0 {
+            true => sentences_achived.insert(id, sentence),
+            false => sentences_backlog.insert(id, sentence),
+        };
+    }
+
+    // Empty collections are stored as null
+    let collection = |achived: Map<String, Value>, backlog: Map<String, Value>| {
+        let or_null = |m: Map<String, Value>| match m.is_empty() {
+            true => Value::Null,
+            false => Value::Object(m),
+        };
+        json!({ "achived": or_null(achived), "backlog": or_null(backlog) })
+    };
+
+    json!({
+        "words": collection(words_achived, words_backlog),
+        "sentences": collection(sentences_achived, sentences_backlog),
+    })
+}
+
+// The entries of both parts of a collection.
+fn entries_mut(collection: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    collection
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|c| c.values_mut())
+        .filter_map(Value::as_object_mut)
+        .flat_map(|m| m.values_mut())
+        .filter_map(Value::as_object_mut)
+}
+
+// Fill in what an unversioned book may lack.
+fn from_unversioned(mut book: Value) -> Value {
+    for entry in entries_mut(book.get_mut("words")) {
+        // Words saved before scheduling are due right away
+        if !entry.contains_key("schedule") {
+            entry.insert(String::from("schedule"), json!(Schedule::default()));
+        }
+        if let Some(id) = entry.remove("sentence_id") {
+            entry.insert(String::from("sentence_ids"), json!([id]));
+        }
+    }
+
+    if let Some(book) = book.as_object_mut() {
+        // Books saved before languages were introduced are Japanese ones
+        if !book.contains_key("language") {
+            book.insert(String::from("language"), json!(Lang::Japanese));
+        }
+        book.insert(String::from("format_version"), json!(2));
+    }
+
+    book
+}
+
+// Move the entries of both collections under the ids made by `id_of`,
+//  returning the new id of every old one.
+// An entry keeps its old id if another one took the new id already.
+fn rekey(
+    collection: Option<&mut Value>,
+    id_of: impl Fn(&Value) -> Option<String>,
+) -> HashMap<String, String> {
+    let mut ids = HashMap::new();
+    let collection = match collection.and_then(Value::as_object_mut) {
+        Some(collection) => collection,
+        None => return ids,
+    };
+
+    let mut taken = HashSet::new();
+    for key in ["achived", "backlog"] {
+        let entries = match collection.get_mut(key).map(Value::take) {
+            Some(Value::Object(entries)) => entries,
+            other => {
+                collection.insert(key.to_owned(), other.unwrap_or(Value::Null));
+                continue;
+            }
+        };
+
+        let mut rekeyed = Map::new();
+        for (old, entry) in entries {
+            let new = id_of(&entry)
+                .filter(|new| !taken.contains(new))
+                .unwrap_or_else(|| old.to_owned());
+            taken.insert(new.to_owned());
+            ids.insert(old, new.to_owned());
+            rekeyed.insert(new, entry);
+        }
+        collection.insert(key.to_owned(), Value::Object(rekeyed));
+    }
+
+    ids
+}
+
+// Replace the ids listed in the `field` of every entry of a collection.
+fn replace_refs(collection: Option<&mut Value>, field: &str, ids: &HashMap<String, String>) {
+    for entry in entries_mut(collection) {
+        let refs = match entry.remove(field) {
+            Some(Value::Array(refs)) => refs,
+            _ => continue,
+        };
+
+        let refs: Vec<Value> = refs
+            .into_iter()
+            .map(|r| match r.as_str().and_then(|r| ids.get(r)) {
+                Some(new) => json!(new),
+                None => r,
+            })
+            .collect();
+        entry.insert(field.to_owned(), Value::Array(refs));
+    }
+}
+
+// Key the words and sentences by a short hash instead of the base64 of their content.
+fn hash_ids(mut book: Value) -> Value {
+    let word_ids = rekey(book.get_mut("words"), |entry| {
+        let word = entry.get("word")?.as_str()?;
+        Some(Word::from(word).id())
+    });
+    let sentence_ids = rekey(book.get_mut("sentences"), |entry| {
+        let sentence = entry.get("sentence")?.as_str()?;
+        Some(Sentence::from(sentence).id())
+    });
+
+    replace_refs(book.get_mut("words"), "sentence_ids", &sentence_ids);
+    replace_refs(book.get_mut("sentences"), "wordentry_ids", &word_ids);
+
+    if let Some(book) = book.as_object_mut() {
+        book.insert(String::from("format_version"), json!(3));
+    }
+
+    book
+}
+
+// Guess the repetitions from the interval, which used to tell the step of the sequence.
+fn count_repetitions(mut book: Value) -> Value {
+    for entry in entries_mut(book.get_mut("words")) {
+        let schedule = match entry.get_mut("schedule").and_then(Value::as_object_mut) {
+            Some(schedule) => schedule,
+            None => continue,
+        };
+
+        let interval = schedule
+            .get("interval")
+            .and_then(Value::as_u64)
+            .unwrap_or(0);
+        schedule.insert(String::from("repetitions"), json!(interval.min(2)));
+    }
+
+    if let Some(book) = book.as_object_mut() {
+        book.insert(String::from("format_version"), json!(4));
+    }
+
+    book
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{load_study_book, study_book::StudyBook};
+
+    fn w_id(word: &str) -> String {
+        Word::from(word).id()
+    }
+
+    const WORDBOOK_JSON: &str = ".test/wordbook.json";
+
+    #[test]
+    fn can_tell_the_versions() {
+        assert_eq!(version_of(&json!({ "entries": {} })), 0);
+        assert_eq!(version_of(&json!({ "words": {} })), 1);
+        assert_eq!(version_of(&json!({ "format_version": 2 })), 2);
+        assert_eq!(upgrade(json!({ "format_version": 99 })).err(), Some(99));
+    }
+
+    #[test]
+    fn can_load_the_legacy_wordbook() {
+        let book: StudyBook = load_study_book(WORDBOOK_JSON).unwrap().unwrap();
+        let s = book.get_status();
+
+        assert_eq!(book.format_version, FORMAT_VERSION);
+        assert_eq!((s.w_backlog, s.s_backlog, s.w_archived), (3, 1, 0));
+
+        let s_id = book.word(&w_id("強")).unwrap().sentence_ids[0].to_owned();
+        let sentence = book.sentence(&s_id).unwrap();
+        assert_eq!(sentence.backlog_volumn, 3);
+        assert_eq!(
+            sentence.wordentry_ids,
+            vec![w_id("経済制裁"), w_id("強"), w_id("広がっています")]
+        );
+    }
+
+    #[test]
+    fn archives_the_mastered_words_of_the_wordbook() {
+        let wordbook = json!({
+            "entries": {
+                "1": { "word": "強", "hiragana": "つよ", "level": 5, "sentence_id": "s" },
+            },
+            "sentences": { "s": { "sentence": "強。", "entry_ids": ["1"] } },
+        });
+        let book: StudyBook = serde_json::from_value(upgrade(wordbook).unwrap()).unwrap();
+
+        assert!(book.is_archived(&w_id("強")));
+        assert_eq!(book.get_status().s_archived, 1);
+
+        // A sentence without words stays in the backlog, as in a book built fresh
+        let wordbook = json!({
+            "entries": {},
+            "sentences": { "s": { "sentence": "強。", "entry_ids": [] } },
+        });
+        let book: StudyBook = serde_json::from_value(upgrade(wordbook).unwrap()).unwrap();
+        let s = book.get_status();
+        assert_eq!((s.s_archived, s.s_backlog), (0, 1));
+    }
+
+    #[test]
+    fn fills_in_the_unversioned_layouts() {
+        let book = json!({
+            "words": {
+                "backlog": {
+                    "1": { "word": "강", "hiragana": "강", "level": 1, "sentence_id": "s" },
+                },
+            },
+            "sentences": { "backlog": null },
+        });
+        let upgraded = from_unversioned(book.clone());
+        let entry = &upgraded["words"]["backlog"]["1"];
+
+        assert_eq!(entry["sentence_ids"], json!(["s"]));
+        assert_eq!(entry.get("sentence_id"), None);
+        assert_eq!(entry["schedule"], json!(Schedule::default()));
+        assert_eq!(upgraded["language"], json!("japanese"));
+        assert_eq!(version_of(&upgraded), 2);
+
+        // What is there already is kept
+        let mut korean = book;
+        korean["language"] = json!("korean");
+        assert_eq!(from_unversioned(korean)["language"], json!("korean"));
+    }
+
+    #[test]
+    fn can_replace_the_base64_ids() {
+        let (word, sentence) = (base64::encode("強"), base64::encode("強まる。"));
+        let book = json!({
+            "format_version": 2,
+            "language": "japanese",
+            "words": {
+                "achived": null,
+                "backlog": {
+                    word.as_str(): { "word": "強", "hiragana": "つよ", "level": 1, "schedule": Schedule::default(), "sentence_ids": [sentence] },
+                },
+            },
+            "sentences": {
+                "backlog": {
+                    sentence.as_str(): { "sentence": "`強`まる。", "backlog_volumn": 1, "wordentry_ids": [word] },
+                },
+            },
+        });
+        let book: StudyBook = serde_json::from_value(upgrade(book).unwrap()).unwrap();
+
+        let entry = book.word(&w_id("強")).unwrap();
+        assert_eq!(entry.sentence_ids, vec![Sentence::from("強まる。").id()]);
+        assert_eq!(
+            book.sentence(&entry.sentence_ids[0]).unwrap().wordentry_ids,
+            vec![w_id("強")]
+        );
+        assert_eq!(book.format_version, FORMAT_VERSION);
+    }
+
+    #[test]
+    fn counts_the_repetitions_from_the_interval() {
+        let schedule =
+            |interval: u64| json!({ "ease": 2.5, "interval": interval, "due": 0, "reviews": 3 });
+        let book = json!({
+            "format_version": 3,
+            "words": {
+                "backlog": {
+                    "a": { "schedule": schedule(0) },
+                    "b": { "schedule": schedule(1) },
+                    "c": { "schedule": schedule(15) },
+                },
+            },
+        });
+        let upgraded = count_repetitions(book);
+        let repetitions =
+            |id: &str| upgraded["words"]["backlog"][id]["schedule"]["repetitions"].to_owned();
+
+        assert_eq!(
+            (repetitions("a"), repetitions("b"), repetitions("c")),
+            (json!(0), json!(1), json!(2))
+        );
+        assert_eq!(version_of(&upgraded), 4);
+    }
+}
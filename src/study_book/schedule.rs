@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DAY: u64 = 60 * 60 * 24;
+
+const EASE_DEFAULT: f32 = 2.5;
+const EASE_MIN: f32 = 1.3;
+
+// Current unix timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// The first second of the day after `ts` (UTC).
+pub fn end_of_day(ts: u64) -> u64 {
+    (ts / DAY + 1) * DAY
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl Grade {
+    // Map the grade onto the 0-5 quality scale of SM-2.
+    fn quality(&self) -> f32 {
+        match self {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+            Grade::Easy => 5.0,
+        }
+    }
+}
+
+// SM-2 scheduling state of a word.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct Schedule {
+    pub ease: f32,
+    pub interval: u32, // in days
+    pub due: u64,      // unix timestamp
+    pub reviews: u32,
+    // Reviews passed since the last lapse, the n of SM-2
+    pub repetitions: u32,
+}
+
+impl Default for Schedule {
+    // A new word is due right away.
+    fn default() -> Self {
+        Schedule {
+            ease: EASE_DEFAULT,
+            interval: 0,
+            due: 0,
+            reviews: 0,
+            repetitions: 0,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn is_due(&self, ts: u64) -> bool {
+        self.due <= ts
+    }
+
+    // Compute the next interval and due date from the graded answer.
+    pub fn review(&mut self, grade: Grade, now: u64) {
+        let q = grade.quality();
+
+        self.reviews += 1;
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(EASE_MIN);
+        // A lapse starts the sequence over: 1 day, 6 days, then growing by the ease
+        self.repetitions = match grade {
+            Grade::Again => 0,
+            _ => self.repetitions + 1,
+        };
+        self.interval = match self.repetitions {
+            0 | 1 => 1,
+            2 => 6,
+            _ => (self.interval as f32 * self.ease).round() as u32,
+        };
+        self.due = now + self.interval as u64 * DAY;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_650_000_000;
+
+    #[test]
+    fn new_word_is_due() {
+        assert!(Schedule::default().is_due(NOW));
+    }
+
+    #[test]
+    fn intervals_grow_with_good_answers() {
+        let mut s = Schedule::default();
+
+        s.review(Grade::Good, NOW);
+        assert_eq!(s.interval, 1);
+        assert_eq!(s.due, NOW + DAY);
+
+        s.review(Grade::Good, NOW);
+        assert_eq!(s.interval, 6);
+
+        s.review(Grade::Good, NOW);
+        assert_eq!(s.interval, 15);
+        assert_eq!(s.reviews, 3);
+        assert!(!s.is_due(NOW));
+    }
+
+    #[test]
+    fn failing_resets_interval_and_lowers_ease() {
+        let mut s = Schedule::default();
+        s.review(Grade::Good, NOW);
+        s.review(Grade::Good, NOW);
+        s.review(Grade::Again, NOW);
+
+        assert_eq!(s.interval, 1);
+        assert_eq!(s.repetitions, 0);
+        assert!(s.ease < EASE_DEFAULT);
+
+        // The sequence starts over
+        let mut relearnt = s.clone();
+        relearnt.review(Grade::Good, NOW);
+        assert_eq!(relearnt.interval, 1);
+        relearnt.review(Grade::Good, NOW);
+        assert_eq!(relearnt.interval, 6);
+        relearnt.review(Grade::Good, NOW);
+        assert!(relearnt.interval > 6);
+
+        for _ in 0..10 {
+            s.review(Grade::Again, NOW);
+        }
+        assert_eq!(s.ease, EASE_MIN);
+    }
+
+    #[test]
+    fn end_of_day_is_next_midnight() {
+        assert_eq!(end_of_day(0), DAY);
+        assert_eq!(end_of_day(DAY - 1), DAY);
+        assert_eq!(end_of_day(DAY), 2 * DAY);
+    }
+}
@@ -1,4 +1,4 @@
-use regex::Regex;
+use crate::id_source::IdSrc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -14,24 +14,65 @@ impl Sentence {
         }
     }
 
+    // The same sentence with other words marked has the same id.
     pub fn id(&self) -> String {
-        let re = Regex::new("`").unwrap();
-        let s = re.replace_all(&self.sentence, "").into_owned();
+        self.id_src().id()
+    }
 
-        base64::encode(s)
+    pub fn id_src(&self) -> IdSrc {
+        IdSrc::from(&self.sentence, |v| v.replace('`', ""))
     }
 
     pub fn sentence(&self) -> &str {
         &self.sentence
     }
+
+    // "...`word_1`...`word_2`..." + word_2
+    //  -> "...other(word_1)...mark(word_2)..."
+    pub fn cloze(
+        &self,
+        word: &str,
+        mark: impl Fn(&str) -> String,
+        other: impl Fn(&str) -> String,
+    ) -> String {
+        self.sentence
+            .split('`')
+            .enumerate()
+            .map(|(i, part)| match i % 2 {
+                // Odd parts are the ones wrapped in backticks
+                1 if part == word => mark(part),
+                1 => other(part),
+                _ => part.to_owned(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct SentenceEntry {
-    pub backlog_volumn: u8,
+    pub backlog_volumn: usize,
     #[serde(flatten)]
     pub sentence: Sentence,
     pub wordentry_ids: Vec<String>,
 }
 
 pub type SentenceEntryMap = HashMap<String, SentenceEntry>; // {id - sentence entyr}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cloze_marks_only_the_target_word() {
+        let s = Sentence::from("トヨタ自動車はあすからロシアにある`工場`の`稼働`を。");
+
+        assert_eq!(
+            s.cloze("稼働", |w| format!("[{}]", w), str::to_owned),
+            "トヨタ自動車はあすからロシアにある工場の[稼働]を。"
+        );
+        assert_eq!(
+            s.cloze("稼働", |w| format!("[{}]", w), |w| format!("({})", w)),
+            "トヨタ自動車はあすからロシアにある(工場)の[稼働]を。"
+        );
+    }
+}
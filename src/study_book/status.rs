@@ -1,6 +1,19 @@
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Status {
     pub w_archived: usize,
     pub w_backlog: usize,
-    pub s_archived:usize,
+    pub s_archived: usize,
     pub s_backlog: usize,
-}
\ No newline at end of file
+}
+
+impl Status {
+    // What has changed since the `before` status.
+    pub fn since(&self, before: &Status) -> Status {
+        Status {
+            w_archived: self.w_archived.saturating_sub(before.w_archived),
+            w_backlog: self.w_backlog.saturating_sub(before.w_backlog),
+            s_archived: self.s_archived.saturating_sub(before.s_archived),
+            s_backlog: self.s_backlog.saturating_sub(before.s_backlog),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use super::{
+    schedule::Schedule,
+    sentence::{Sentence, SentenceEntry},
+    word::{Word, WordEntry},
+    StudyBook,
+};
+use crate::{
+    error::{Error, Result},
+    language::Lang,
+};
+use regex::Regex;
+use std::{cell::Cell, collections::HashMap, str::FromStr};
+
+// A column of the table, by its position counted from 1 or by its header.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Header(String),
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().parse::<usize>() {
+            Ok(0) => Err(String::from("columns are counted from 1")),
+            Ok(i) => Ok(Column::Index(i)),
+            Err(_) => Ok(Column::Header(s.trim().to_owned())),
+        }
+    }
+}
+
+// Where the fields of the words are in the table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnMapping {
+    pub word: Column,
+    pub reading: Column,
+    pub meaning: Option<Column>,
+    pub sentence: Option<Column>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            word: Column::Index(1),
+            reading: Column::Index(2),
+            meaning: Some(Column::Index(3)),
+            sentence: Some(Column::Index(4)),
+        }
+    }
+}
+
+pub struct TableFormat {
+    pub delimiter: u8,
+    // Whether the first row names the columns rather than holding a word.
+    pub has_headers: bool,
+    pub columns: ColumnMapping,
+}
+
+impl TableFormat {
+    pub fn csv() -> TableFormat {
+        TableFormat {
+            delimiter: b',',
+            has_headers: true,
+            columns: ColumnMapping::default(),
+        }
+    }
+
+    pub fn tsv() -> TableFormat {
+        TableFormat {
+            delimiter: b'\t',
+            ..TableFormat::csv()
+        }
+    }
+}
+
+// The position of the column in the rows.
+fn resolve(column: &Column, headers: Option<&csv::StringRecord>) -> Result<usize> {
+    match (column, headers) {
+        (Column::Index(i), _) => Ok(i - 1),
+        (Column::Header(name), Some(headers)) => headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::Table(format!("there is no column named {}", name))),
+        (Column::Header(name), None) => Err(Error::Table(format!(
+            "the column {} can't be found in a table without headers",
+            name
+        ))),
+    }
+}
+
+// Sentences exported from Anki have the word as a cloze deletion,
+//  turn it into the backticks of the markups.
+fn uncloze(sentence: &str) -> String {
+    let cloze = Regex::new(r"\{\{c\d+::(.*?)(?:::.*?)?\}\}").unwrap();
+
+    cloze.replace_all(sentence, "`$1`").into_owned()
+}
+
+// Mark the word in the example sentence with backticks, as the markups would.
+// None if the word isn't in the sentence, as it couldn't be blanked out in a cloze.
+fn mark_word(sentence: &str, word: &str) -> Option<String> {
+    let mark = |w: &str| format!("`{}`", w);
+    let marked = Cell::new(false);
+    let mut sentence = Sentence::from(sentence).cloze(
+        word,
+        |w| {
+            marked.set(true);
+            mark(w)
+        },
+        mark,
+    );
+    if marked.get() {
+        return Some(sentence);
+    }
+
+    // Find it out of the other marked words, hidden under as many bytes
+    let hide = |w: &str| "\0".repeat(w.len() + 2);
+    let start = Sentence::from(&sentence)
+        .cloze(word, hide, hide)
+        .find(word)?;
+    sentence.replace_range(start..start + word.len(), &mark(word));
+
+    Some(sentence)
+}
+
+impl StudyBook {
+    // Build a book from a vocabulary list, one word per row.
+    // Rows with a word but no reading are rejected, blank rows are skipped.
+    // A word missing from its example sentence is imported without it.
+    pub fn from_table(content: &str, format: &TableFormat, language: Lang) -> Result<StudyBook> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(format.delimiter)
+            .has_headers(format.has_headers)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .from_reader(content.as_bytes());
+
+        let headers = match format.has_headers {
+            true => Some(
+                reader
+                    .headers()
+                    .map_err(|err| Error::Table(err.to_string()))?
+                    .clone(),
+            ),
+            false => None,
+        };
+        let columns = &format.columns;
+        let word_col = resolve(&columns.word, headers.as_ref())?;
+        let reading_col = resolve(&columns.reading, headers.as_ref())?;
+        let meaning_col = match &columns.meaning {
+            Some(c) => Some(resolve(c, headers.as_ref())?),
+            None => None,
+        };
+        let sentence_col = match &columns.sentence {
+            Some(c) => Some(resolve(c, headers.as_ref())?),
+            None => None,
+        };
+
+        let mut words: HashMap<String, WordEntry> = HashMap::new();
+        let mut sentences: HashMap<String, SentenceEntry> = HashMap::new();
+
+        for record in reader.records() {
+            let record = record.map_err(|err| Error::Table(err.to_string()))?;
+            let line = record.position().map_or(0, |p| p.line());
+            let cell = |col: Option<usize>| {
+                col.and_then(|c| record.get(c))
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+            };
+
+            if record.iter().all(|c| c.trim().is_empty()) {
+                continue;
+            }
+            let word = cell(Some(word_col))
+                .ok_or_else(|| Error::Table(format!("no word on line {}", line)))?;
+            let reading = cell(Some(reading_col))
+                .ok_or_else(|| Error::Table(format!("no reading for {} on line {}", word, line)))?;
+            let annotation = cell(meaning_col).map(String::from);
+
+            let id = Word::from(word).id();
+            let entry = words.entry(id.clone()).or_insert_with(|| WordEntry {
+                annotation: None,
+                hiragana: reading.to_owned(),
+                level: 1,
+                schedule: Schedule::default(),
+                sentence_ids: vec![],
+                word: Word::from(word),
+            });
+            if entry.word.word() != word {
+                return Err(Error::IdCollision(
+                    entry.word.word().to_owned(),
+                    word.to_owned(),
+                ));
+            }
+            if entry.annotation.is_none() {
+                entry.annotation = annotation;
+            }
+
+            // Link the word to its example sentence
+            if let Some(s) = cell(sentence_col) {
+                let s = Sentence::from(&uncloze(s));
+                let s_id = s.id();
+
+                // Mark the word along with the ones of the previous rows
+                let content = s.id_src().content();
+                let marked = match sentences.get(&s_id) {
+                    Some(s_entry) if s_entry.sentence.id_src().content() != content => {
+                        return Err(Error::IdCollision(
+                            s_entry.sentence.id_src().content(),
+                            content,
+                        ));
+                    }
+                    Some(s_entry) => mark_word(s_entry.sentence.sentence(), word),
+                    None => mark_word(s.sentence(), word),
+                };
+                let marked = match marked {
+                    Some(marked) => marked,
+                    None => continue,
+                };
+
+                entry.add_sentence(s_id.clone());
+                let s_entry = sentences.entry(s_id).or_insert_with(|| SentenceEntry {
+                    backlog_volumn: 0,
+                    sentence: s,
+                    wordentry_ids: vec![],
+                });
+                s_entry.sentence = Sentence::from(&marked);
+
+                if !s_entry.wordentry_ids.contains(&id) {
+                    s_entry.wordentry_ids.push(id);
+                    s_entry.backlog_volumn += 1;
+                }
+            }
+        }
+
+        Ok(StudyBook::from_backlog(language, words, sentences))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "Meaning,Kanji,Kana,Example
+sanctions,経済制裁,けいざいせいさい,ロシアへの経済制裁が強まる。
+\"factory, plant\",工場,こうじょう,
+
+strong,強,つよ,ロシアへの経済制裁が強まる。
+";
+
+    #[test]
+    fn can_import_a_table_by_headers() {
+        let format = TableFormat {
+            columns: ColumnMapping {
+                word: "kanji".parse().unwrap(),
+                reading: "Kana".parse().unwrap(),
+                meaning: Some("1".parse().unwrap()),
+                sentence: Some("Example".parse().unwrap()),
+            },
+            ..TableFormat::csv()
+        };
+        let book = StudyBook::from_table(TABLE, &format, Lang::Japanese).unwrap();
+        let s = book.get_status();
+        assert_eq!((s.w_backlog, s.s_backlog), (3, 1));
+
+        let factory = book.word(&Word::from("工場").id()).unwrap();
+        assert_eq!(factory.annotation.as_deref(), Some("factory, plant"));
+        assert!(factory.sentence_ids.is_empty());
+
+        let strong = book.word(&Word::from("強").id()).unwrap();
+        let sentence = book.sentence(&strong.sentence_ids[0]).unwrap();
+        assert_eq!(
+            sentence.sentence.sentence(),
+            "ロシアへの`経済制裁`が`強`まる。"
+        );
+        assert_eq!(sentence.backlog_volumn, 2);
+    }
+
+    #[test]
+    fn can_import_an_anki_export() {
+        let tsv = StudyBook::from_article("ロシアへの<<経済制裁・けいざいせいさい>>が強まる。")
+            .unwrap()
+            .to_anki_tsv();
+        let format = TableFormat {
+            has_headers: false,
+            columns: ColumnMapping {
+                word: Column::Index(2),
+                reading: Column::Index(3),
+                meaning: Some(Column::Index(4)),
+                sentence: Some(Column::Index(1)),
+            },
+            ..TableFormat::tsv()
+        };
+        let book = StudyBook::from_table(&tsv, &format, Lang::Japanese).unwrap();
+
+        let id = Word::from("経済制裁").id();
+        let entry = book.word(&id).unwrap();
+        assert_eq!(entry.hiragana, "けいざいせいさい");
+        assert_eq!(
+            book.sentence(&entry.sentence_ids[0])
+                .unwrap()
+                .sentence
+                .sentence(),
+            "ロシアへの`経済制裁`が強まる。"
+        );
+    }
+
+    #[test]
+    fn marks_the_word_out_of_the_marked_ones() {
+        assert_eq!(
+            mark_word("`強まる`が強い。", "強").as_deref(),
+            Some("`強まる`が`強`い。")
+        );
+        assert_eq!(mark_word("`強`まる。", "強").as_deref(), Some("`強`まる。"));
+        assert_eq!(mark_word("`強まる`。", "強"), None);
+    }
+
+    #[test]
+    fn leaves_out_sentences_without_the_word() {
+        let table = "word,reading,meaning,sentence\n工場,こうじょう,factory,ロシアにある工場。\n強,つよ,strong,ロシアにある工場。\n";
+        let book = StudyBook::from_table(table, &TableFormat::csv(), Lang::Japanese).unwrap();
+
+        let strong = book.word(&Word::from("強").id()).unwrap();
+        assert!(strong.sentence_ids.is_empty());
+
+        let factory = book.word(&Word::from("工場").id()).unwrap();
+        let sentence = book.sentence(&factory.sentence_ids[0]).unwrap();
+        assert_eq!(sentence.sentence.sentence(), "ロシアにある`工場`。");
+        assert_eq!(sentence.wordentry_ids, vec![Word::from("工場").id()]);
+    }
+
+    #[test]
+    fn can_reject_rows_without_reading() {
+        let res = StudyBook::from_table("word,reading\n強,\n", &TableFormat::csv(), Lang::Japanese);
+        assert!(matches!(res, Err(Error::Table(msg)) if msg.contains("line 2")));
+
+        let format = TableFormat {
+            columns: ColumnMapping {
+                word: "Kanji".parse().unwrap(),
+                ..ColumnMapping::default()
+            },
+            ..TableFormat::csv()
+        };
+        let res = StudyBook::from_table("word,reading\n", &format, Lang::Japanese);
+        assert!(matches!(res, Err(Error::Table(_))));
+    }
+}
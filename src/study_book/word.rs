@@ -1,3 +1,5 @@
+use super::schedule::{Grade, Schedule};
+use crate::{id_source::IdSrc, language::Language};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -12,7 +14,7 @@ impl Word {
     }
 
     pub fn id(&self) -> String {
-        base64::encode(&self.word)
+        IdSrc::new(&self.word).id()
     }
 
     pub fn word(&self) -> &str {
@@ -25,7 +27,8 @@ pub struct WordEntry {
     pub annotation: Option<String>,
     pub hiragana: String,
     pub level: u8,
-    pub sentence_id: String,
+    pub schedule: Schedule,
+    pub sentence_ids: Vec<String>,
     #[serde(flatten)]
     pub word: Word,
 }
@@ -34,9 +37,84 @@ impl WordEntry {
     pub fn set_level(&mut self, level: u8) {
         self.level = level;
     }
+
+    pub fn add_sentence(&mut self, id: String) -> bool {
+        match self.sentence_ids.contains(&id) {
+            true => false,
+            false => {
+                self.sentence_ids.push(id);
+                true
+            }
+        }
+    }
+
+    // The sentence to show in the next review, rotating between the contexts.
+    pub fn context(&self) -> Option<&str> {
+        match self.sentence_ids.len() {
+            0 => None,
+            n => Some(&self.sentence_ids[self.schedule.reviews as usize % n]),
+        }
+    }
+
+    // Record a review: update the level and schedule the next one.
+    pub fn review(&mut self, grade: Grade, now: u64) {
+        match grade {
+            Grade::Again => self.set_level(1),
+            _ => self.set_level(self.level.saturating_add(1)),
+        }
+
+        self.schedule.review(grade, now);
+    }
+
+    // Compare the answer with the reading, once both normalized for the language.
+    pub fn check_reading(&self, answer: &str, language: &dyn Language) -> bool {
+        language.normalize_answer(answer) == language.normalize(&self.hiragana)
+    }
 }
 
 pub type WordEntryMap = HashMap<String, WordEntry>; // {id - word entry}
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_check_reading() {
+        let e = WordEntry {
+            annotation: None,
+            hiragana: String::from("こうじょう"),
+            level: 1,
+            schedule: Schedule::default(),
+            sentence_ids: vec![String::from("1")],
+            word: Word::from("工場"),
+        };
+
+        let ja = crate::language::Lang::Japanese.profile();
+
+        assert!(e.check_reading(" こうじょう ", ja));
+        assert!(e.check_reading("コウジョウ", ja));
+        assert!(e.check_reading("koujou", ja));
+        assert!(!e.check_reading("こうば", ja));
+    }
+
+    #[test]
+    fn can_rotate_contexts() {
+        let mut e = WordEntry {
+            annotation: None,
+            hiragana: String::from("こうじょう"),
+            level: 1,
+            schedule: Schedule::default(),
+            sentence_ids: vec![String::from("1")],
+            word: Word::from("工場"),
+        };
+
+        assert!(e.add_sentence(String::from("2")));
+        assert!(!e.add_sentence(String::from("2")));
+
+        assert_eq!(e.context(), Some("1"));
+        e.review(Grade::Good, 0);
+        assert_eq!(e.context(), Some("2"));
+        e.review(Grade::Good, 0);
+        assert_eq!(e.context(), Some("1"));
+    }
+}
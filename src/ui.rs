@@ -1,14 +1,104 @@
-use std::{fmt, vec};
+use std::{fmt, io, vec};
 
-use console::Term;
-use dialoguer::{theme::ColorfulTheme, Input, Select};
+use crate::{
+    error::{Error, Result},
+    parser::diagnostic::Diagnostic,
+    study_book::merge::MergeReport,
+};
 
-pub fn request_raw_content() -> Result<String, &'static str> {
-    match Input::with_theme(&ColorfulTheme::default()).with_prompt("Please input some content with valid markups.")
-    .default("トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。".into())
-    .interact_text() {
+use console::{style, Term};
+use dialoguer::{theme::ColorfulTheme, Confirm, Editor, Input, Select};
+
+const EXAMPLE_CONTENT: &str = "トヨタ自動車はあすからロシアにある<<工場・こうじょう>>の<<稼働・かどう・operation of a machine, running>>を<<停止・ていし>>すると<<発表・はっぴょう>>しました。";
+
+// Ask for content, prefilled with `default` (e.g. the last invalid input) or an example.
+pub fn request_raw_content(default: Option<&str>) -> Result<String> {
+    match Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Please input some content with valid markups.")
+        .default(default.unwrap_or(EXAMPLE_CONTENT).into())
+        .interact_text()
+    {
         Ok(input) => Ok(input),
-        Err(_) => Err("Failed to get the input"),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+// Shown above the content in the editor, and left out of what is read back.
+const EDITOR_HELP: &str =
+    "# Write the content to add, marking the words to learn like <<工場・こうじょう・factory>>.
+# Lines starting with # are ignored.
+";
+
+// The content written in the editor, without the lines of EDITOR_HELP.
+fn strip_editor_help(content: &str) -> String {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Open the content in the user's $VISUAL or $EDITOR, prefilled with `default`
+//  (e.g. the last invalid input) below a short help.
+pub fn request_content_in_editor(default: Option<&str>) -> Result<String> {
+    let text = format!("{}{}", EDITOR_HELP, default.unwrap_or_default());
+
+    match Editor::new().edit(&text) {
+        Ok(Some(content)) => match strip_editor_help(&content) {
+            content if content.trim().is_empty() => Err(Error::Ui(io::Error::other(
+                "nothing was written in the editor",
+            ))),
+            content => Ok(content),
+        },
+        Ok(None) => Err(Error::Ui(io::Error::other(
+            "the editor was closed without saving",
+        ))),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+pub fn request_file_path() -> Result<String> {
+    match Input::<String>::with_theme(&ColorfulTheme::default())
+        .with_prompt("Which file should I read?")
+        .interact_text()
+    {
+        Ok(input) => Ok(input),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum InputMethod {
+    Prompt,
+    Editor,
+    File,
+}
+
+impl fmt::Display for InputMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let printable = match *self {
+            InputMethod::Prompt => "Type it here",
+            InputMethod::Editor => "Open my editor, for articles of many lines",
+            InputMethod::File => "Read it from a file",
+        };
+        write!(f, "{}", printable)
+    }
+}
+
+pub fn choose_input_method() -> Result<InputMethod> {
+    let options = vec![InputMethod::Prompt, InputMethod::Editor, InputMethod::File];
+
+    match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("How do you want to add the content?")
+        .items(&options)
+        .default(0)
+        .interact_on_opt(&Term::stderr())
+    {
+        Ok(res) => match res {
+            Some(index) => Ok(options[index]),
+            None => Ok(options[0]),
+        },
+        Err(err) => Err(Error::Ui(err)),
     }
 }
 
@@ -28,7 +118,7 @@ impl fmt::Display for NextStep {
     }
 }
 
-pub fn study_or_add_more() -> Result<NextStep, &'static str> {
+pub fn study_or_add_more() -> Result<NextStep> {
     let options = vec![NextStep::Study, NextStep::AddMore];
 
     match Select::with_theme(&ColorfulTheme::default())
@@ -40,6 +130,127 @@ pub fn study_or_add_more() -> Result<NextStep, &'static str> {
             Some(index) => Ok(options[index].clone()),
             None => Ok(options[0].clone()),
         },
-        Err(_) => Err("Failed to get the input"),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+// Pick one of the names, e.g. of the books of the library, starting on `current`.
+pub fn choose_name(prompt: &str, names: &[String], current: Option<&str>) -> Result<String> {
+    let default = names
+        .iter()
+        .position(|n| Some(n.as_str()) == current)
+        .unwrap_or(0);
+
+    match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(names)
+        .default(default)
+        .interact_on_opt(&Term::stderr())
+    {
+        Ok(res) => Ok(names[res.unwrap_or(default)].clone()),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+pub fn request_name() -> Result<String> {
+    match Input::<String>::with_theme(&ColorfulTheme::default())
+        .with_prompt("Hello! What's your name?")
+        .interact_text()
+    {
+        Ok(input) => Ok(input.trim().to_owned()),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+pub fn confirm(prompt: &str) -> Result<bool> {
+    match Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(false)
+        .interact_on(&Term::stderr())
+    {
+        Ok(answer) => Ok(answer),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+// Pick one of the choices, None to quit.
+pub fn choose_answer(prompt: &str, choices: &[String]) -> Result<Option<usize>> {
+    match Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(choices)
+        .default(0)
+        .interact_on_opt(&Term::stderr())
+    {
+        Ok(res) => Ok(res),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+pub fn request_answer(prompt: &str) -> Result<String> {
+    match Input::<String>::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()
+    {
+        Ok(input) => Ok(input),
+        Err(err) => Err(Error::Ui(err)),
+    }
+}
+
+// Tell what was added to the book, and what was kept out of it.
+pub fn show_merge_report(report: &MergeReport) {
+    println!(
+        "Added {} words of {} new sentences.",
+        report.new_words.len(),
+        report.new_sentences.len()
+    );
+    if !report.conflicts.is_empty() {
+        println!(
+            "{} words were already in the book with a different reading or annotation, the ones with more progress are kept.",
+            report.conflicts.len()
+        );
+    }
+    if !report.collisions.is_empty() {
+        println!(
+            "{} entries were left out as they have the same id as different ones in the book.",
+            report.collisions.len()
+        );
+    }
+}
+
+// Print the input with the invalid markups highlighted, followed by the problems.
+pub fn show_diagnostics(input: &str, diagnostics: &[Diagnostic]) {
+    let highlighted: String = input
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            match diagnostics
+                .iter()
+                .any(|d| i >= d.offset && i < d.offset + d.len)
+            {
+                true => style(c).red().bold().underlined().to_string(),
+                false => c.to_string(),
+            }
+        })
+        .collect();
+
+    println!("{}", highlighted);
+    for d in diagnostics {
+        println!("  {} {}", style("✘").red(), d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_the_editor_help() {
+        let content = format!("{}ロシアにある<<工場・こうじょう>>。\n二行目", EDITOR_HELP);
+        assert_eq!(
+            strip_editor_help(&content),
+            "ロシアにある<<工場・こうじょう>>。\n二行目"
+        );
+        assert!(strip_editor_help(EDITOR_HELP).is_empty());
     }
 }